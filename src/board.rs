@@ -15,9 +15,31 @@ pub trait Board: Display + Clone + Debug + Eq {
 
 #[derive(Debug, Clone)]
 pub struct BasicBoard {
-    candidates: [[bool; 9]; 81],
-    cells: [u8; 81],
-    rules: Rules,
+    pub(crate) candidates: [[bool; 9]; 81],
+    pub(crate) cells: [u8; 81],
+    pub(crate) rules: Rules,
+}
+
+impl BasicBoard {
+    /// Copies the completed cells and the fully built rule sets out of any other board,
+    /// so a different engine can work on the same puzzle.
+    pub fn from_board<B: Board>(board: &B) -> BasicBoard {
+        let cells = board.completed_cells();
+        let mut candidates = [[true; 9]; 81];
+
+        for (index, cell) in cells.iter().enumerate() {
+            if *cell != 0 {
+                candidates[index] = [false; 9];
+                candidates[index][*cell as usize - 1] = true;
+            }
+        }
+
+        BasicBoard {
+            candidates,
+            cells,
+            rules: board.rules().clone(),
+        }
+    }
 }
 
 impl Board for BasicBoard {
@@ -1,5 +1,5 @@
 use std::{fs::File, io::Read, path::Path, time::Instant};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 
 mod rules;
@@ -7,6 +7,8 @@ pub use rules::Rules;
 
 mod solve;
 pub use solve::*;
+use solve::trad_solver::{LogicOptions, Profile, SolveOutcome};
+use solve::z3_solver::Uniqueness;
 
 mod board;
 pub use board::Board;
@@ -16,7 +18,12 @@ pub use board::Board;
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Name of the .sudoku file or the sudoku string itself
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Name of the .sudoku file or the sudoku string itself. Killer cages go on lines of their
+    /// own after the grid, like "15 = r1c1 r1c2 r2c1". A second block of 81 region letters
+    /// after the grid makes it a jigsaw sudoku
     #[arg(short, long)]
     sudoku: String,
 
@@ -28,9 +35,98 @@ struct Args {
     #[arg(short, long, default_value_t = false)]
     rubiks_rules: bool,
 
+    /// Diagonal (Sudoku-X) rules, both main diagonals hold every digit once
+    #[arg(short = 'x', long, default_value_t = false)]
+    diagonal_rules: bool,
+
+    /// Windoku rules, four extra 3x3 windows hold every digit once
+    #[arg(short, long, default_value_t = false)]
+    windoku_rules: bool,
+
+    /// Cells a knight's move apart can't hold the same digit
+    #[arg(long, default_value_t = false)]
+    anti_knight: bool,
+
+    /// Cells a king's move apart can't hold the same digit
+    #[arg(long, default_value_t = false)]
+    anti_king: bool,
+
+    /// Digits that can't be a queen's move apart, as a comma separated list like "9" or "1,9"
+    #[arg(long, value_delimiter = ',', value_parser = clap::value_parser!(u8).range(1..=9))]
+    anti_queen: Vec<u8>,
+
     /// Stop the solve if we need to bifurcate
     #[arg(long, default_value_t = false)]
     stop_if_bifurcate: bool,
+
+    /// Also solve with the Z3 backend and cross-check the result
+    #[arg(long, default_value_t = false)]
+    z3: bool,
+
+    /// If there's no solution, name the smallest set of givens and rules that clash
+    #[arg(long, default_value_t = false)]
+    explain: bool,
+
+    /// Count the solutions instead of solving, stopping once this many have been found
+    #[arg(long)]
+    count: Option<usize>,
+
+    /// Print every logical step taken before any guessing is needed
+    #[arg(long, default_value_t = false)]
+    steps: bool,
+
+    /// When stepping through the logic (--steps, hint, grade), try forcing chains that follow up
+    /// to this many singles before giving up. A plain solve guesses instead and ignores it
+    #[arg(long)]
+    forcing_depth: Option<usize>,
+
+    /// Use unique rectangles and BUG+1, which only work if the puzzle has a single solution
+    #[arg(long, default_value_t = false)]
+    assume_unique: bool,
+
+    /// Techniques for the logic engine to use, in order: a built in profile (default, singles,
+    /// no-chains), a file with one technique per line, or a comma separated list. Techniques
+    /// are named as the steps print them and can be given a grading weight like "Naked Pair = 3.5"
+    #[arg(long)]
+    profile: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Show only the easiest next logical step instead of solving
+    Hint,
+    /// Rate how hard the puzzle is for the logic engine. Files with one 81 character puzzle per
+    /// line are graded line by line
+    Grade {
+        /// More puzzle files to grade along with the one given by --sudoku
+        files: Vec<String>,
+    },
+}
+
+/// Reads `source` as a file if there is one, otherwise takes it as the sudoku string itself.
+fn read_source(source: &str) -> String {
+    let mut sudoku_string = String::new();
+
+    if let Ok(mut file) = File::open(source) {
+        let _ = file.read_to_string(&mut sudoku_string).unwrap();
+    } else {
+        sudoku_string = source.to_string();
+    }
+
+    sudoku_string
+}
+
+/// Splits a batch of puzzles, one per line, into separate puzzles. Anything else, like the
+/// usual nine line grid, is a single puzzle.
+fn split_batch(contents: &str) -> Vec<String> {
+    let is_cell = |ch: &char| matches!(ch, '-' | '0'..='9' | '*' | '.' | '_');
+    let lines: Vec<&str> = contents.lines().filter(|line| !line.trim().is_empty()).collect();
+
+    if !lines.is_empty() && lines.iter().all(|line| line.chars().filter(is_cell).count() >= 81) {
+        lines.into_iter().map(String::from).collect()
+    } else {
+        vec![contents.to_string()]
+    }
 }
 
 fn main() {
@@ -40,28 +136,159 @@ fn main() {
     let rules = Rules {
         normal_sudoku: args.normal_rules,
         rubiks: args.rubiks_rules, 
-        sets: vec![]
+        diagonals: args.diagonal_rules,
+        windoku: args.windoku_rules,
+        sets: vec![],
+        regions: vec![],
+        cages: vec![],
+        anti_knight: args.anti_knight,
+        anti_king: args.anti_king,
+        anti_queen: args.anti_queen.iter().fold(0, |acc, digit| acc | (1 << (digit - 1))),
     };
 
-    let mut sudoku_string = String::new();
+    let sudoku_string = read_source(&args.sudoku);
+
+    let profile = match &args.profile {
+        None => Profile::default(),
+        Some(name) => match Profile::preset(name).map_or_else(|| Profile::parse(&read_source(name)), Ok) {
+            Ok(profile) => profile,
+            Err(error) => {
+                println!("Bad profile: {}.", error);
+                return;
+            }
+        },
+    };
 
-    if let Ok(mut file) = File::open(&args.sudoku) {
-        let _ = file.read_to_string(&mut sudoku_string).unwrap();
-    } else {
-        sudoku_string = args.sudoku.clone();
+    let options = LogicOptions {
+        forcing_depth: args.forcing_depth,
+        assume_unique: args.assume_unique,
+        profile,
+    };
+
+    if let Some(Command::Grade {files}) = &args.command {
+        for source in std::iter::once(&args.sudoku).chain(files.iter()) {
+            let puzzles = split_batch(&read_source(source));
+
+            for (line, puzzle) in puzzles.iter().enumerate() {
+                let grade = match solve::trad_solver::BitmaskBoard::from_string(puzzle.clone(), rules.clone()) {
+                    Ok(brd) => brd.with_options(options.clone()).grade().to_string(),
+                    Err(error) => format!("bad puzzle: {}", error),
+                };
+
+                if puzzles.len() > 1 {
+                    println!("{}:{}: {}", source, line + 1, grade);
+                } else {
+                    println!("{}: {}", source, grade);
+                }
+            }
+        }
+        return;
+    }
+
+    let mut brd = match solve::trad_solver::BitmaskBoard::from_string(sudoku_string, rules.clone()) {
+        Ok(brd) => brd.with_options(options),
+        Err(error) => {
+            println!("Bad puzzle: {}.", error);
+            return;
+        }
+    };
+    let unsolved = brd.clone();
+
+    if let Some(Command::Hint) = &args.command {
+        if let Some(reason) = brd.explain_illegal() {
+            println!("The givens already break the rules: {}.", reason);
+            return;
+        }
+
+        match brd.hint() {
+            Some(step) => {println!("{}", step);}
+            None if brd.is_solved() => {println!("Already solved.");}
+            None => {println!("No logical step found, you'll have to guess.");}
+        }
+        return;
     }
 
-    let mut brd = solve::trad_solver::BitmaskBoard::from_string(sudoku_string, rules.clone());
+    if let Some(limit) = args.count {
+        let count = brd.solutions(Some(limit)).count();
 
-    let mut recursion_count = 0;
+        let plural = if count == 1 {""} else {"s"};
 
-    let solns = brd.solve(&mut recursion_count);
+        if count == limit {
+            println!("At least {count} solution{plural}.");
+        } else {
+            println!("{count} solution{plural}.");
+        }
+        return;
+    }
 
-    if solns == 1 {
-        println!("1 solution:\n{}",brd);
-    } else {
-        println!("Uh oh! {solns} solutions.\n");
+    if args.steps {
+        let mut logic = brd.clone();
+        let mut replay = brd.clone();
+
+        for step in logic.solve_logically() {
+            println!("{}", step);
+
+            if let Some(colors) = step.colors {
+                println!("{}", replay.with_colors(colors));
+            }
+
+            replay.apply_step(&step);
+        }
+
+        println!("{}", logic);
+    }
+
+    let mut recursion_count = 0;
+
+    let outcome = brd.solve(&mut recursion_count, args.stop_if_bifurcate);
+
+    match &outcome {
+        SolveOutcome::NoSolution => {
+            println!("Uh oh! No solutions.\n");
+
+            if let Some(reason) = unsolved.explain_illegal() {
+                println!("The givens already break the rules: {}.", reason);
+            }
+
+            if args.explain {
+                match board::BasicBoard::from_board(&unsolved).explain_unsat() {
+                    Some(conflict) => {println!("No solution because {}.", conflict);}
+                    None => {println!("Z3 found a solution anyway, the bitmask solver is wrong here.");}
+                }
+            }
+        }
+        SolveOutcome::Unique(solution) => {
+            println!("1 solution:\n{}", solution);
+        }
+        SolveOutcome::Multiple {first, second} => {
+            println!("Multiple solutions, for example:\n{}\n{}", first, second);
+        }
+        SolveOutcome::NeedsGuessing(partial) => {
+            println!("Stopped because logic alone can't go any further:\n{}", partial);
+        }
+        SolveOutcome::Aborted {reason, stats} => {
+            println!("Gave up after {} guesses ({}).\n", stats.recursion_count, reason);
+        }
+    }
 
+    if args.z3 {
+        let mut z3_brd = board::BasicBoard::from_board(&unsolved);
+
+        match z3_brd.check_uniqueness() {
+            Uniqueness::Unsat => {println!("Z3 found no solution.");}
+            Uniqueness::Unique => {println!("Z3 found a unique solution:\n{}", z3_brd);}
+            Uniqueness::Multiple(second) => {
+                println!("Z3 found multiple solutions, for example:\n{}\n{}", z3_brd, second);
+            }
+        }
+
+        if let SolveOutcome::Unique(_) = outcome {
+            if z3_brd.completed_cells() == brd.completed_cells() {
+                println!("Z3 agrees with the bitmask solver.");
+            } else {
+                println!("Z3 disagrees with the bitmask solver!");
+            }
+        }
     }
 
     let mut sudoku_string_slice = ['-'; 81];
@@ -1,8 +1,130 @@
+pub mod chess;
+pub mod constraint;
+pub mod jigsaw;
+pub mod killer;
 pub mod sudoku;
 
+pub use chess::{AntiMove, Piece};
+pub use constraint::{Constraint, UniqueSet};
+pub use jigsaw::{is_region_block, parse_regions};
+pub use killer::Cage;
+
 #[derive(Clone, Debug)]
 pub struct Rules {
     pub normal_sudoku: bool,
     pub rubiks: bool,
-    pub sets: Vec<u128>
-}
\ No newline at end of file
+    /// Sudoku-X: both main diagonals hold every digit once.
+    pub diagonals: bool,
+    /// Windoku: so do the four windows set one cell in from the boxes.
+    pub windoku: bool,
+    pub sets: Vec<u128>,
+    /// Jigsaw regions read from the puzzle file, which take the place of the boxes.
+    pub regions: Vec<u128>,
+    /// Killer cages, which unlike the sets don't need every digit.
+    pub cages: Vec<Cage>,
+    /// Cells a knight's move apart can't hold the same digit.
+    pub anti_knight: bool,
+    /// Nor can cells a king's move apart, i.e. diagonal neighbours.
+    pub anti_king: bool,
+    /// Digits (as a bitmask of digit indexes) that can't be a queen's move apart.
+    pub anti_queen: u16,
+}
+
+const RUBIKS_COLORS: [&str; 6] = ["red", "orange", "yellow", "green", "blue", "white"];
+
+impl Rules {
+    /// Human readable name for one of the sets, e.g. "row 3", "box 5" or "red face".
+    pub fn describe_set(&self, set_index: usize) -> String {
+        let set = self.sets[set_index];
+
+        if let Some(region) = self.regions.iter().position(|region| *region == set) {
+            return format!("region {}", region + 1);
+        }
+
+        for a in 0..9 {
+            if set == row_set(a) {
+                return format!("row {}", a + 1);
+            } else if set == column_set(a) {
+                return format!("column {}", a + 1);
+            } else if set == box_set(a) {
+                return format!("box {}", a + 1);
+            }
+        }
+
+        if set == diagonal_set(false) {
+            return "main diagonal".to_string();
+        } else if set == diagonal_set(true) {
+            return "anti-diagonal".to_string();
+        }
+
+        for window in 0..4 {
+            if set == window_set(window) {
+                return format!("window {}", window + 1);
+            }
+        }
+
+        // the rubik's faces are always the last six sets pushed by from_string
+        if self.rubiks && set_index + 6 >= self.sets.len() {
+            return format!("{} face", RUBIKS_COLORS[set_index + 6 - self.sets.len()]);
+        }
+
+        format!("set {}", set_index + 1)
+    }
+
+    /// The chess move rules, as constraints over pairs of cells.
+    pub fn anti_moves(&self) -> Vec<AntiMove> {
+        let mut moves = vec![];
+
+        if self.anti_knight {
+            moves.push(AntiMove {piece: Piece::Knight, digits: 0x1FF});
+        }
+
+        if self.anti_king {
+            moves.push(AntiMove {piece: Piece::King, digits: 0x1FF});
+        }
+
+        if self.anti_queen != 0 {
+            moves.push(AntiMove {piece: Piece::Queen, digits: self.anti_queen});
+        }
+
+        moves
+    }
+
+    /// Whether the standard 3x3 boxes are among the sets, which some techniques lean on.
+    pub fn has_boxes(&self) -> bool {
+        self.normal_sudoku && self.regions.is_empty()
+    }
+}
+
+/// Bitmask of row `row`, counting from 0.
+pub fn row_set(row: usize) -> u128 {
+    0x1FF << (row * 9)
+}
+
+/// Bitmask of column `column`, counting from 0.
+pub fn column_set(column: usize) -> u128 {
+    (0..9).fold(0, |acc, row| acc | (1 << (row * 9 + column)))
+}
+
+/// Bitmask of box `sudoku_box`, counting from 0 in reading order.
+pub fn box_set(sudoku_box: usize) -> u128 {
+    let top_left = (sudoku_box / 3) * 27 + (sudoku_box % 3) * 3;
+    (0..9).fold(0, |acc, cell| acc | (1 << (top_left + (cell / 3) * 9 + cell % 3)))
+}
+
+/// Bitmask of the diagonal from r1c1 to r9c9, or from r1c9 to r9c1 if `anti`.
+pub fn diagonal_set(anti: bool) -> u128 {
+    (0..9).fold(0, |acc, row| acc | (1 << (row * 9 + if anti {8 - row} else {row})))
+}
+
+/// Bitmask of Windoku window `window`, counting from 0 in reading order. The windows are the
+/// 3x3 blocks starting at r2c2, r2c6, r6c2 and r6c6.
+pub fn window_set(window: usize) -> u128 {
+    let top_left = (1 + (window / 2) * 4) * 9 + 1 + (window % 2) * 4;
+    (0..9).fold(0, |acc, cell| acc | (1 << (top_left + (cell / 3) * 9 + cell % 3)))
+}
+
+/// Standard r#c# name of a cell index.
+pub fn describe_cell(index: u8) -> String {
+    format!("r{}c{}", index / 9 + 1, index % 9 + 1)
+}
@@ -0,0 +1,148 @@
+use std::fmt;
+use std::sync::OnceLock;
+
+use super::constraint::Constraint;
+use super::describe_cell;
+
+const KNIGHT_STEPS: [(i8, i8); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+// a queen takes the same steps as a king, just as far as it likes
+const KING_STEPS: [(i8, i8); 8] = [(0, 1), (1, 1), (1, 0), (1, -1), (0, -1), (-1, -1), (-1, 0), (-1, 1)];
+
+/// A chess piece, for the "no two cells a piece's move apart hold the same digit" rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Piece {
+    Knight,
+    King,
+    Queen,
+}
+
+impl Piece {
+    /// Per cell, every cell the piece could move to from there on an empty board.
+    pub fn moves(&self) -> &'static [u128; 81] {
+        static KNIGHT: OnceLock<[u128; 81]> = OnceLock::new();
+        static KING: OnceLock<[u128; 81]> = OnceLock::new();
+        static QUEEN: OnceLock<[u128; 81]> = OnceLock::new();
+
+        match self {
+            Piece::Knight => KNIGHT.get_or_init(|| move_table(&KNIGHT_STEPS, false)),
+            Piece::King => KING.get_or_init(|| move_table(&KING_STEPS, false)),
+            Piece::Queen => QUEEN.get_or_init(|| move_table(&KING_STEPS, true)),
+        }
+    }
+}
+
+impl fmt::Display for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Piece::Knight => write!(f, "knight"),
+            Piece::King => write!(f, "king"),
+            Piece::Queen => write!(f, "queen"),
+        }
+    }
+}
+
+/// Masks of the cells reached by taking each step once, or as many times as fits if `slide`.
+fn move_table(steps: &[(i8, i8)], slide: bool) -> [u128; 81] {
+    let mut table = [0; 81];
+
+    for (index, moves) in table.iter_mut().enumerate() {
+        let (row, column) = ((index / 9) as i8, (index % 9) as i8);
+
+        for (row_step, column_step) in steps {
+            let (mut r, mut c) = (row + row_step, column + column_step);
+
+            while (0..9).contains(&r) && (0..9).contains(&c) {
+                *moves |= 1 << (r * 9 + c);
+
+                if !slide {
+                    break;
+                }
+
+                (r, c) = (r + row_step, c + column_step);
+            }
+        }
+    }
+
+    table
+}
+
+/// Cells a `piece`'s move apart can't both hold one of `digits`, a bitmask of digit indexes.
+/// Anti-knight and anti-king cover every digit, anti-queen usually only one or two.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AntiMove {
+    pub piece: Piece,
+    pub digits: u16,
+}
+
+impl AntiMove {
+    fn has(&self, digit: usize) -> bool {
+        self.digits & (1 << digit) != 0
+    }
+
+    /// Two filled in cells a move apart that hold the same digit, if there are any.
+    fn clash(&self, candidates: &[u128; 9], complete: u128) -> Option<(u8, u8, usize)> {
+        for digit in (0..9).filter(|d| self.has(*d)) {
+            let mut solved = candidates[digit] & complete;
+
+            while solved != 0 {
+                let index = solved.trailing_zeros() as u8;
+                let attacked = self.piece.moves()[index as usize] & candidates[digit] & complete;
+
+                if attacked != 0 {
+                    return Some((index, attacked.trailing_zeros() as u8, digit));
+                }
+
+                solved &= solved - 1;
+            }
+        }
+
+        None
+    }
+}
+
+impl Constraint for AntiMove {
+    fn describe(&self) -> String {
+        self.to_string()
+    }
+
+    // peers are the same for every digit, so a constraint on only some digits has none
+    fn peers(&self, index: u8) -> u128 {
+        if self.digits == 0x1FF {self.piece.moves()[index as usize]} else {0}
+    }
+
+    fn propagate(&self, candidates: &mut [u128; 9], complete: u128) {
+        for digit in (0..9).filter(|d| self.has(*d)) {
+            let mut solved = candidates[digit] & complete;
+
+            while solved != 0 {
+                let index = solved.trailing_zeros() as usize;
+                candidates[digit] &= !self.piece.moves()[index];
+                solved &= solved - 1;
+            }
+        }
+    }
+
+    fn is_legal(&self, candidates: &[u128; 9], complete: u128) -> bool {
+        self.clash(candidates, complete).is_none()
+    }
+
+    fn explain(&self, candidates: &[u128; 9], complete: u128) -> String {
+        match self.clash(candidates, complete) {
+            Some((a, b, digit)) => format!("{} and {} are a {}'s move apart and both {}", describe_cell(a), describe_cell(b), self.piece, digit + 1),
+            None => format!("{} can't be satisfied", self),
+        }
+    }
+}
+
+impl fmt::Display for AntiMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "anti-{}", self.piece)?;
+
+        if self.digits != 0x1FF {
+            let digits: Vec<String> = (0..9).filter(|d| self.has(*d)).map(|d| (d + 1).to_string()).collect();
+            write!(f, " on {}", digits.join(","))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+use std::fmt;
+
+/// One rule of the puzzle as the bitmask solver enforces it. `candidates` is a bitmask of cells
+/// per digit and `complete` the cells that are filled in, the same as in `BitmaskBoard`.
+pub trait Constraint: fmt::Debug {
+    /// Human readable name, e.g. "row 3" or "15 cage at r1c1".
+    fn describe(&self) -> String;
+
+    /// Cells that can't hold the same digit as cell `index` because of this constraint.
+    fn peers(&self, index: u8) -> u128;
+
+    /// Removes the candidates that the filled in cells rule out.
+    fn propagate(&self, candidates: &mut [u128; 9], complete: u128);
+
+    /// Whether the board could still be completed as far as this constraint can tell.
+    fn is_legal(&self, candidates: &[u128; 9], complete: u128) -> bool;
+
+    /// What is wrong with the board, for when `is_legal` says it's broken.
+    fn explain(&self, candidates: &[u128; 9], complete: u128) -> String {
+        let _ = (candidates, complete);
+        format!("{} can't be satisfied", self.describe())
+    }
+}
+
+/// Cells that hold every digit exactly once, like a row, column, box or rubik's face.
+#[derive(Debug, Clone)]
+pub struct UniqueSet {
+    pub cells: u128,
+    pub name: String,
+}
+
+impl Constraint for UniqueSet {
+    fn describe(&self) -> String {
+        self.name.clone()
+    }
+
+    fn peers(&self, index: u8) -> u128 {
+        if self.cells & (1 << index) != 0 {self.cells & !(1 << index)} else {0}
+    }
+
+    fn propagate(&self, candidates: &mut [u128; 9], complete: u128) {
+        let complete = self.cells & complete;
+
+        for mask in candidates.iter_mut() {
+            let solved = complete & *mask;
+
+            if solved != 0 {
+                *mask &= !(self.cells & !solved);
+            }
+        }
+    }
+
+    fn is_legal(&self, candidates: &[u128; 9], complete: u128) -> bool {
+        candidates.iter().all(|mask| mask & self.cells != 0 && (mask & self.cells & complete).count_ones() <= 1)
+    }
+
+    fn explain(&self, candidates: &[u128; 9], complete: u128) -> String {
+        for (digit, mask) in candidates.iter().enumerate() {
+            if mask & self.cells == 0 {
+                return format!("{} has nowhere left for a {}", self.name, digit + 1);
+            } else if (mask & self.cells & complete).count_ones() > 1 {
+                return format!("{} has more than one {}", self.name, digit + 1);
+            }
+        }
+
+        format!("{} can't be satisfied", self.name)
+    }
+}
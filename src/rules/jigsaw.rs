@@ -0,0 +1,81 @@
+use anyhow::{bail, Result};
+
+use super::describe_cell;
+
+/// Reads the jigsaw regions from 81 region letters in reading order, one region per letter.
+/// Every region has to be 9 cells joined up by their sides.
+pub fn parse_regions(letters: &[char]) -> Result<Vec<u128>> {
+    if letters.len() != 81 {
+        bail!("the region block has {} letters instead of 81", letters.len());
+    }
+
+    let mut names: Vec<char> = vec![];
+    let mut regions: Vec<u128> = vec![];
+
+    for (index, letter) in letters.iter().enumerate() {
+        match names.iter().position(|name| name == letter) {
+            Some(region) => regions[region] |= 1 << index,
+            None => {
+                names.push(*letter);
+                regions.push(1 << index);
+            }
+        }
+    }
+
+    for (name, region) in names.iter().zip(&regions) {
+        if region.count_ones() != 9 {
+            bail!("region {} has {} cells instead of 9", name, region.count_ones());
+        }
+
+        if !is_connected(*region) {
+            bail!("region {} isn't in one piece, {} is cut off", name, describe_cell(cut_off(*region)));
+        }
+    }
+
+    Ok(regions)
+}
+
+/// Whether trailing letters after the grid make up a jigsaw layout at all: 81 of them, nine
+/// different letters nine times each. Anything else isn't meant as regions.
+pub fn is_region_block(letters: &[char]) -> bool {
+    let mut names = letters.to_vec();
+    names.sort_unstable();
+    names.dedup();
+
+    letters.len() == 81 && names.len() == 9
+        && names.iter().all(|name| letters.iter().filter(|letter| *letter == name).count() == 9)
+}
+
+/// Cells of `cells` reachable from its first cell by stepping between neighbouring cells.
+fn flood(cells: u128) -> u128 {
+    let mut reached: u128 = 1 << cells.trailing_zeros();
+
+    loop {
+        // cells on the left edge can't step left, nor the right edge right
+        let left_edge = (0..9).fold(0u128, |acc, row| acc | (1 << (row * 9)));
+        let right_edge = left_edge << 8;
+
+        let grown = reached
+            | (reached << 9)
+            | (reached >> 9)
+            | ((reached & !right_edge) << 1)
+            | ((reached & !left_edge) >> 1);
+
+        let grown = grown & cells;
+
+        if grown == reached {
+            return reached;
+        }
+
+        reached = grown;
+    }
+}
+
+fn is_connected(cells: u128) -> bool {
+    flood(cells) == cells
+}
+
+/// A cell that `flood` doesn't reach, for the error message.
+fn cut_off(cells: u128) -> u8 {
+    (cells & !flood(cells)).trailing_zeros() as u8
+}
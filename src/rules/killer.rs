@@ -0,0 +1,160 @@
+use std::fmt;
+use std::sync::OnceLock;
+
+use anyhow::{bail, Result};
+
+use super::constraint::Constraint;
+use super::describe_cell;
+
+/// A killer cage: cells that add up to `sum` without repeating a digit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cage {
+    pub cells: u128,
+    pub sum: u8,
+}
+
+impl Cage {
+    /// Reads a cage written as its sum and its cells, like `15 = r1c1 r1c2 r2c1`.
+    pub fn parse(line: &str) -> Result<Cage> {
+        let (sum, cells) = match line.split_once('=') {
+            Some(parts) => parts,
+            None => bail!("a cage is written like \"15 = r1c1 r1c2\""),
+        };
+
+        let sum: u8 = match sum.trim().parse() {
+            Ok(sum) => sum,
+            Err(_) => bail!("\"{}\" isn't a sum", sum.trim()),
+        };
+
+        let mut mask: u128 = 0;
+
+        for cell in cells.split(|ch: char| ch.is_whitespace() || ch == ',').filter(|cell| !cell.is_empty()) {
+            let index = match parse_cell(cell) {
+                Some(index) => index,
+                None => bail!("\"{}\" isn't a cell", cell),
+            };
+
+            if mask & (1 << index) != 0 {
+                bail!("{} is in the cage twice", cell);
+            }
+
+            mask |= 1 << index;
+        }
+
+        let size = mask.count_ones() as usize;
+
+        if size == 0 || size > 9 {
+            bail!("a cage has 1 to 9 cells");
+        }
+
+        if cage_combinations(size, sum as usize).is_empty() {
+            bail!("{} different digits can't add up to {}", size, sum);
+        }
+
+        Ok(Cage {cells: mask, sum})
+    }
+
+    /// The combinations of digits the cage could still be filled with, given per digit masks of
+    /// the cells that can have it.
+    pub fn combinations_left(&self, candidates: &[u128; 9]) -> Vec<u16> {
+        let cells: Vec<u16> = (0..81u8)
+            .filter(|i| self.cells & (1 << i) != 0)
+            .map(|i| (0..9).filter(|d| candidates[*d] & (1 << i) != 0).fold(0, |acc, d| acc | (1 << d)))
+            .collect();
+
+        cage_combinations(cells.len(), self.sum as usize).iter()
+            .copied()
+            .filter(|combination| fits(&cells, *combination))
+            .collect()
+    }
+}
+
+impl Constraint for Cage {
+    fn describe(&self) -> String {
+        self.to_string()
+    }
+
+    fn peers(&self, index: u8) -> u128 {
+        if self.cells & (1 << index) != 0 {self.cells & !(1 << index)} else {0}
+    }
+
+    // no repeats, the sum is left to the cage sum technique
+    fn propagate(&self, candidates: &mut [u128; 9], complete: u128) {
+        let complete = self.cells & complete;
+
+        for mask in candidates.iter_mut() {
+            let solved = complete & *mask;
+
+            if solved != 0 {
+                *mask &= !(self.cells & !solved);
+            }
+        }
+    }
+
+    fn is_legal(&self, candidates: &[u128; 9], complete: u128) -> bool {
+        candidates.iter().all(|mask| (mask & self.cells & complete).count_ones() <= 1)
+            && !self.combinations_left(candidates).is_empty()
+    }
+
+    fn explain(&self, candidates: &[u128; 9], complete: u128) -> String {
+        match candidates.iter().position(|mask| (mask & self.cells & complete).count_ones() > 1) {
+            Some(digit) => format!("the {} has more than one {}", self, digit + 1),
+            None => format!("nothing left adds up to {} in the {}", self.sum, self),
+        }
+    }
+}
+
+/// Whether cells with these candidates can take the digits of `combination`, one each.
+fn fits(cells: &[u16], combination: u16) -> bool {
+    let mut reachable = vec![0u16];
+
+    for candidates in cells {
+        let mut next = vec![];
+
+        for used in &reachable {
+            let free = candidates & combination & !used;
+            next.extend((0..9).filter(|d| free & (1 << d) != 0).map(|d| used | (1 << d)));
+        }
+
+        next.sort_unstable();
+        next.dedup();
+        reachable = next;
+    }
+
+    !reachable.is_empty()
+}
+
+impl fmt::Display for Cage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} cage at {}", self.sum, describe_cell(self.cells.trailing_zeros() as u8))
+    }
+}
+
+/// Reads an r#c# cell name into a cell index.
+fn parse_cell(cell: &str) -> Option<u8> {
+    let (row, column) = cell.to_lowercase().strip_prefix('r')?.split_once('c')
+        .map(|(row, column)| (row.parse::<u8>(), column.parse::<u8>()))?;
+
+    match (row, column) {
+        (Ok(row @ 1..=9), Ok(column @ 1..=9)) => Some((row - 1) * 9 + column - 1),
+        _ => None,
+    }
+}
+
+/// Every set of `size` different digits that adds up to `sum`, as 9 bit digit masks.
+pub fn cage_combinations(size: usize, sum: usize) -> &'static [u16] {
+    static TABLE: OnceLock<Vec<Vec<Vec<u16>>>> = OnceLock::new();
+
+    let table = TABLE.get_or_init(|| {
+        let mut table = vec![vec![vec![]; 46]; 10];
+
+        for digits in 0..0x200u16 {
+            let total: usize = (0..9).filter(|d| digits & (1 << d) != 0).map(|d| d + 1).sum();
+            table[digits.count_ones() as usize][total].push(digits);
+        }
+
+        table
+    });
+
+    table.get(size).and_then(|sums| sums.get(sum)).map_or(&[], |combinations| combinations.as_slice())
+}
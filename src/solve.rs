@@ -2,5 +2,6 @@ pub mod trad_solver;
 pub mod z3_solver;
 
 pub trait Solve {
-    fn solve(&mut self);
-}
\ No newline at end of file
+    /// Fills in the board, returning false if no solution exists.
+    fn solve(&mut self) -> bool;
+}
@@ -1,16 +1,35 @@
-use std::{fmt, fs, path::Path};
-use anyhow::Result;
+use std::{fmt, fs, path::Path, rc::Rc};
+use anyhow::{bail, Result};
 use crate::Board;
 use crate::Rules;
+use crate::rules::{box_set, describe_cell, diagonal_set, is_region_block, parse_regions, window_set, Cage, Constraint, UniqueSet};
+
+mod als;
+mod chains;
+mod coloring;
+mod fish;
+mod forcing;
+mod grade;
+mod intersections;
+mod killer;
+mod profile;
+mod single_digit;
+mod steps;
+mod subsets;
+mod uniqueness;
+mod wings;
+pub use grade::{Category, Grade};
+pub use profile::Profile;
+pub use steps::{Step, Technique};
 
 impl Board for BitmaskBoard {
     fn completed_cells(&self) -> [u8; 81] {
         let mut cells = [0; 81];
         for i in 0..81 {
             if self.is_complete(i) {
-                for c in 1..=9 {
+                for c in 0..9 {
                     if self.digit_is_candidate(i, c) {
-                        cells[i as usize] = c as u8;
+                        cells[i as usize] = c as u8 + 1;
                         break;
                     }
                 } 
@@ -40,17 +59,34 @@ fn pretty_print_bitmask(bits: u128) -> String {
     )
 }
 
-fn get_set_indexes(set: u128) -> Vec<u8> {
+/// Every way of picking `size` items out of `items`, keeping their order.
+fn combinations<T: Copy>(items: &[T], size: usize) -> Vec<Vec<T>> {
+    if size == 0 {
+        return vec![vec![]];
+    }
+
     let mut v = vec![];
-    for i in 0..81 {
-        if (set >> i) % 2 != 0 {
-            v.push(i)
+    for (i, item) in items.iter().enumerate() {
+        for mut rest in combinations(&items[i + 1..], size - 1) {
+            rest.insert(0, *item);
+            v.push(rest);
         }
     }
 
     v
 }
 
+fn get_set_indexes(set: u128) -> Vec<u8> {
+    let mut v = vec![];
+    let mut rest = set;
+    while rest != 0 {
+        v.push(rest.trailing_zeros() as u8);
+        rest &= rest - 1;
+    }
+
+    v
+}
+
 
 
 #[derive(Clone)]
@@ -58,7 +94,97 @@ pub struct BitmaskBoard {
     pub candidates: [u128; 9],
     cell_complete: u128,
     rules: Rules,
-    rubiks_sets: Option<[u128; 6]> 
+    rubiks_sets: Option<[u128; 6]>,
+    /// Two classes of cells to highlight when displaying, e.g. the colours of a colouring step.
+    colors: Option<[u128; 2]>,
+    options: LogicOptions,
+    /// The rules as the solver enforces them, built from `rules` once the puzzle is read.
+    constraints: Rc<Vec<Box<dyn Constraint>>>,
+    /// Per cell, every cell the constraints stop from having the same digit.
+    peer_masks: Rc<[u128; 81]>,
+}
+
+/// Settings for the logic engine that aren't part of the puzzle's rules.
+#[derive(Debug, Clone, Default)]
+pub struct LogicOptions {
+    /// Try forcing chains that follow up to this many singles before guessing.
+    pub forcing_depth: Option<usize>,
+    /// Allow deductions that only hold if the puzzle has a single solution.
+    pub assume_unique: bool,
+    /// The techniques to use, in order, and what each counts for when grading.
+    pub profile: Profile,
+}
+
+/// How a call to `BitmaskBoard::solve` ended. Boards are boxed to keep the outcome small.
+#[derive(Debug, Clone)]
+pub enum SolveOutcome {
+    NoSolution,
+    Unique(Box<BitmaskBoard>),
+    Multiple {first: Box<BitmaskBoard>, second: Box<BitmaskBoard>},
+    /// Logic alone got stuck and `stop_if_bifurcate` was set, holds the board as far as it got.
+    NeedsGuessing(Box<BitmaskBoard>),
+    Aborted {reason: AbortReason, stats: SolveStats},
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbortReason {
+    /// More than 10000 guesses were needed.
+    RecursionLimit,
+    /// The logic loop kept changing the board for more than 10000 rounds.
+    TooManyIterations,
+}
+
+impl fmt::Display for AbortReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AbortReason::RecursionLimit => write!(f, "too many guesses"),
+            AbortReason::TooManyIterations => write!(f, "too many iterations"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SolveStats {
+    pub recursion_count: u128,
+    pub iterations: usize,
+}
+
+/// Iterator over the completions of a board, see `BitmaskBoard::solutions`.
+pub struct Solutions {
+    stack: Vec<BitmaskBoard>,
+    remaining: Option<usize>,
+}
+
+impl Iterator for Solutions {
+    type Item = BitmaskBoard;
+
+    fn next(&mut self) -> Option<BitmaskBoard> {
+        if self.remaining == Some(0) {
+            return None;
+        }
+
+        while let Some(mut brd) = self.stack.pop() {
+            if !brd.propagate() {
+                continue;
+            }
+
+            if brd.is_solved() {
+                self.remaining = self.remaining.map(|r| r - 1);
+                return Some(brd);
+            }
+
+            let (index, _) = brd.find_lowest_candidates_unsolved();
+
+            // pushed backwards so that the lowest digit gets tried first
+            for c in brd.candidates_vec(index).into_iter().rev() {
+                let mut new_sudoku = brd.clone();
+                new_sudoku.set_cell(c, index);
+                self.stack.push(new_sudoku);
+            }
+        }
+
+        None
+    }
 }
 
 // #[derive(Clone)]
@@ -124,13 +250,7 @@ impl fmt::Display for BitmaskBoard {
 
 
         for row in 0..9 {
-
-            match row {
-                0 =>           {write!(f, "┏━━━━━━━┯━━━━━━━┯━━━━━━━┳━━━━━━━┯━━━━━━━┯━━━━━━━┳━━━━━━━┯━━━━━━━┯━━━━━━━┓\n")?;}
-                1|2|4|5|7|8 => {write!(f, "┠───────┼───────┼───────╂───────┼───────┼───────╂───────┼───────┼───────┨\n")?;}
-                3|6 =>         {write!(f, "┣━━━━━━━┿━━━━━━━┿━━━━━━━╋━━━━━━━┿━━━━━━━┿━━━━━━━╋━━━━━━━┿━━━━━━━┿━━━━━━━┫\n")?;}
-                _ => {unreachable!("got past end of for loop somehow")}
-            }
+            write!(f, "{}", self.generate_border(row))?;
 
             for line in 0..3 {
                 write!(f, "{}", self.generate_line_from_bitmask(row, line))?;
@@ -138,11 +258,14 @@ impl fmt::Display for BitmaskBoard {
         }
 
 
-        write!(f, "┗━━━━━━━┷━━━━━━━┷━━━━━━━┻━━━━━━━┷━━━━━━━┷━━━━━━━┻━━━━━━━┷━━━━━━━┷━━━━━━━┛\n")
+        write!(f, "{}", self.generate_border(9))
 
     }
 }
 
+/// Crossings of the grid lines, indexed by which arms are thick: 1 up, 2 down, 4 left, 8 right.
+const CROSSES: [char; 16] = ['┼', '╀', '╁', '╂', '┽', '╃', '╅', '╉', '┾', '╄', '╆', '╊', '┿', '╇', '╈', '╋'];
+
 const BIG_CHAR: [[&'static str; 3]; 9] = [
     ["  111  ",
      "   11  ",
@@ -233,6 +356,14 @@ impl BitmaskBoard {
             }
         }
 
+        if let Some(colors) = self.colors {
+            if (1 << index) & colors[0] != 0 {
+                code = "\x1b[46m\x1b[30m";
+            } else if (1 << index) & colors[1] != 0 {
+                code = "\x1b[45m";
+            }
+        }
+
 
     
 
@@ -263,7 +394,8 @@ impl BitmaskBoard {
         let mut s = String::new();
 
         for column in 0..9 {
-            s.push(if column % 3 == 0 {'┃'} else {'│'});
+            let index = self.bitmask_index(row, column);
+            s.push(if column == 0 || !self.same_region(index - 1, index) {'┃'} else {'│'});
             s.push_str(self.cell_candidates_partial_string(row, column, line).as_str());
         }
 
@@ -272,6 +404,54 @@ impl BitmaskBoard {
         s
     }
 
+    /// The line drawn above `row`, or below the last row for 9. It's thick wherever it crosses
+    /// from one box (or jigsaw region) into another.
+    fn generate_border(&self, row: u8) -> String {
+        // thick across the top of `column`, and down the left of `column` above and below the line
+        let across = |column: u8| row == 0 || row == 9 || !self.same_region((row - 1) * 9 + column, row * 9 + column);
+        let up = |column: u8| row > 0 && (column == 0 || !self.same_region((row - 1) * 9 + column - 1, (row - 1) * 9 + column));
+        let down = |column: u8| row < 9 && (column == 0 || !self.same_region(row * 9 + column - 1, row * 9 + column));
+
+        let mut s = String::new();
+
+        for column in 0..9 {
+            let junction = match (row, column) {
+                (0, 0) => '┏',
+                (9, 0) => '┗',
+                (0, _) => if down(column) {'┳'} else {'┯'},
+                (9, _) => if up(column) {'┻'} else {'┷'},
+                (_, 0) => if across(0) {'┣'} else {'┠'},
+                _ => {
+                    let heavy = up(column) as usize | (down(column) as usize) << 1
+                        | (across(column - 1) as usize) << 2 | (across(column) as usize) << 3;
+                    CROSSES[heavy]
+                }
+            };
+
+            s.push(junction);
+            s.push_str(if across(column) {"━━━━━━━"} else {"───────"});
+        }
+
+        s.push(match row {
+            0 => '┓',
+            9 => '┛',
+            _ => if across(8) {'┫'} else {'┨'},
+        });
+        s.push('\n');
+
+        s
+    }
+
+    /// Whether two cells are in the same box, or the same region of a jigsaw.
+    fn same_region(&self, a: u8, b: u8) -> bool {
+        if self.rules.regions.is_empty() {
+            let ((row_a, column_a), (row_b, column_b)) = (self.rc_from_index(a), self.rc_from_index(b));
+            self.get_box(row_a, column_a) == self.get_box(row_b, column_b)
+        } else {
+            self.rules.regions.iter().any(|region| region & (1 << a) != 0 && region & (1 << b) != 0)
+        }
+    }
+
 
 
 
@@ -284,6 +464,10 @@ impl BitmaskBoard {
             cell_complete: 0, 
             rules: rules.clone(),
             rubiks_sets: None,
+            colors: None,
+            options: LogicOptions::default(),
+            constraints: Rc::new(vec![]),
+            peer_masks: Rc::new([0; 81]),
         };
 
         if brd.rules.normal_sudoku {
@@ -324,19 +508,57 @@ impl BitmaskBoard {
             }
         }
 
+        if brd.rules.diagonals {
+            brd.rules.sets.push(diagonal_set(false));
+            brd.rules.sets.push(diagonal_set(true));
+        }
+
+        if brd.rules.windoku {
+            for window in 0..4 {
+                brd.rules.sets.push(window_set(window));
+            }
+        }
+
         for set in brd.rules.sets.clone() {
             // println!("{:#083b}", set);
             // println!("{}", pretty_print_bitmask(set));
             assert_eq!(set.count_ones(), 9);
         }
 
+        brd.build_constraints();
+
         brd
     }
 
+    /// Turns the sets, cages and chess moves of the rules into the constraints the solver works with.
+    fn build_constraints(&mut self) {
+        let mut constraints: Vec<Box<dyn Constraint>> = vec![];
+
+        for (set_index, set) in self.rules.sets.iter().enumerate() {
+            constraints.push(Box::new(UniqueSet {cells: *set, name: self.rules.describe_set(set_index)}));
+        }
+
+        for cage in &self.rules.cages {
+            constraints.push(Box::new(*cage));
+        }
+
+        for anti_move in self.rules.anti_moves() {
+            constraints.push(Box::new(anti_move));
+        }
+
+        let mut peer_masks = [0; 81];
+        for (index, peers) in peer_masks.iter_mut().enumerate() {
+            *peers = constraints.iter().fold(0, |acc, constraint| acc | constraint.peers(index as u8));
+        }
+
+        self.constraints = Rc::new(constraints);
+        self.peer_masks = Rc::new(peer_masks);
+    }
+
     pub fn from_file(file_path: &Path, rules: Rules) -> Result<BitmaskBoard> {
         let mut s = fs::read_to_string(file_path)?;
 
-        let mut b = BitmaskBoard::from_string(s, rules);
+        let mut b = BitmaskBoard::from_string(s, rules)?;
 
         b.update_cell_complete();
 
@@ -344,7 +566,9 @@ impl BitmaskBoard {
 
     }
 
-    pub fn from_string(s: String, rules: Rules) -> BitmaskBoard {
+    /// Reads a puzzle, along with any killer cages, rubik's colours or jigsaw regions after the
+    /// grid. Mistakes in those come back as an error message.
+    pub fn from_string(s: String, rules: Rules) -> Result<BitmaskBoard> {
 
         let mut b = BitmaskBoard::new(rules);
 
@@ -360,7 +584,31 @@ impl BitmaskBoard {
         let mut b_set: u128 = 0;
         let mut w_set: u128 = 0;
 
-        for ch in s.chars() {
+        let mut region_letters: Vec<char> = vec![];
+
+        // killer cages go on lines of their own, like "15 = r1c1 r1c2 r2c1"
+        let mut grid = String::new();
+
+        for line in s.lines() {
+            if !line.contains('=') {
+                grid.push_str(line);
+                grid.push('\n');
+                continue;
+            }
+
+            let cage = match Cage::parse(line) {
+                Ok(cage) => cage,
+                Err(error) => {bail!("bad cage \"{}\": {}", line.trim(), error)},
+            };
+
+            if let Some(other) = b.rules.cages.iter().find(|other| other.cells & cage.cells != 0) {
+                bail!("the {} overlaps the {}", cage, other);
+            }
+
+            b.rules.cages.push(cage);
+        }
+
+        for ch in grid.chars() {
             if idx >= 81 {
                 if b.rules.rubiks {
                     match ch {
@@ -373,6 +621,10 @@ impl BitmaskBoard {
                         'X' | '*' | '.' | '_' => {},
                         _ => {continue},
                     }
+                } else if ch.is_ascii_alphabetic() {
+                    region_letters.push(ch);
+                } else {
+                    continue;
                 }
 
             } else {
@@ -409,7 +661,22 @@ impl BitmaskBoard {
             b.rubiks_sets = Some([r_set, o_set, y_set, g_set, b_set, w_set]);
         }
 
-        b
+        // a jigsaw's regions replace the boxes, any other letters are left alone
+        if is_region_block(&region_letters) {
+            let regions = match parse_regions(&region_letters) {
+                Ok(regions) => regions,
+                Err(error) => {bail!("bad jigsaw regions: {}", error)},
+            };
+
+            b.rules.sets.retain(|set| !(0..9).any(|i| *set == box_set(i)));
+            b.rules.sets.extend(regions.iter().copied());
+            b.rules.regions = regions;
+        }
+
+        b.build_constraints();
+        b.update_cell_complete();
+
+        Ok(b)
     }
 
 
@@ -476,6 +743,16 @@ impl BitmaskBoard {
         return_val
     }
 
+    /// Every cell that can't have the same digit as this one.
+    fn peers(&self, index: u8) -> u128 {
+        self.peer_masks[index as usize]
+    }
+
+    /// Cells that see every cell in `cells`.
+    fn seen_by_all(&self, cells: u128) -> u128 {
+        get_set_indexes(cells).into_iter().fold(u128::MAX, |acc, i| acc & self.peers(i))
+    }
+
     fn find_lowest_candidates_unsolved(&self) -> (u8, u8) {
         let mut minimum: (u8, u8) = (10, 200);
                     
@@ -497,23 +774,8 @@ impl BitmaskBoard {
     }
 
     pub fn is_legal(&self) -> bool {
-        for set in &self.rules.sets {
-            for digit in 0..9 {
-                let candidates = self.candidates[digit];
-                let set_candidates = candidates & set;
-                if set_candidates.count_ones() == 0 { // if this digit has no candidates in the set
-                    // println!("{}s: {}: set_candidates: {}", digit, pretty_print_bitmask(candidates), pretty_print_bitmask(set_candidates));
-                    // println!("No {} found in set {}", digit + 1, pretty_print_bitmask(*set));
-                    return false;
-                }
-
-                let set_solved_digits = self.cell_complete & set_candidates;
-
-                if set_solved_digits.count_ones() > 1 { // if there's more than one "solved" number in here.
-                    // println!("No {} found in set {}", digit + 1, pretty_print_bitmask(*set));
-                    return false;
-                }
-            }
+        if !self.constraints.iter().all(|constraint| constraint.is_legal(&self.candidates, self.cell_complete)) {
+            return false;
         }
 
         // probably isn't necessary but whatever.
@@ -526,6 +788,20 @@ impl BitmaskBoard {
         true
     }
 
+    /// Says which rule the board already breaks once the candidates are worked out, if any.
+    pub fn explain_illegal(&self) -> Option<String> {
+        let mut brd = self.clone();
+        brd.update_cell_complete();
+        brd.update_candidates();
+
+        if let Some(constraint) = brd.constraints.iter().find(|constraint| !constraint.is_legal(&brd.candidates, brd.cell_complete)) {
+            return Some(constraint.explain(&brd.candidates, brd.cell_complete));
+        }
+
+        (0..81).find(|index| brd.get_candidates(*index) == 0)
+            .map(|index| format!("{} has no candidates left", describe_cell(index)))
+    }
+
     pub fn is_solved(&self) -> bool {
         self.cell_complete == 0x000000000001FFFFFFFFFFFFFFFFFFFF
     }
@@ -558,121 +834,222 @@ impl BitmaskBoard {
     }
 
     fn update_candidates(&mut self) {
-        for set in &self.rules.sets {
-
-            let complete = set & self.cell_complete; // 1s where there are complete cells in this set
+        for constraint in self.constraints.iter() {
+            constraint.propagate(&mut self.candidates, self.cell_complete);
+        }
+    }
 
-            for digit in 0..9 {
-                let cells_containing_digit = complete & self.candidates[digit];
-                let set_contains_digit = cells_containing_digit.count_ones() != 0;
 
-                //assert!(cells_containing_digit.count_ones() <= 1);
 
-                let mut eliminate_mask = set * (set_contains_digit as u128);
-                eliminate_mask &= !cells_containing_digit;
 
-                let mask = !eliminate_mask;
 
-                self.candidates[digit] &= mask;
+    // solve ---------------------------------------------------------------------------------------------
 
-            }
-        }
+    fn optimize(&mut self) -> Option<Step> {
+        let step = self.next_step()?;
+        self.apply_step(&step);
+        Some(step)
     }
 
+    /// The first deduction found by the techniques of the profile, tried in order. Techniques
+    /// that share a finder are looked for together where the first of them comes.
+    fn next_step(&self) -> Option<Step> {
+        let mut searched = vec![];
 
+        self.options.profile.techniques.iter().find_map(|(technique, _)| {
+            if searched.contains(&technique.finder()) {
+                return None;
+            }
 
+            searched.push(technique.finder());
+            self.find_step(*technique)
+        })
+    }
 
+    /// Applies a step found by `hint` or `solve_logically` to this board.
+    pub fn apply_step(&mut self, step: &Step) {
+        for digit in 0..9 {
+            self.candidates[digit] &= !step.eliminations[digit];
 
-    // solve ---------------------------------------------------------------------------------------------
+            for index in get_set_indexes(step.placements[digit]) {
+                self.set_cell(digit, index);
+                self.cell_complete |= 1 << index;
+            }
+        }
 
-    fn optimize(&mut self) {
-        if self.hidden_naked_singles_pairs_triples() {return}
+        self.update_candidates();
+    }
 
+
+    /// Full houses, hidden singles and naked singles, the only things forcing chains follow.
+    fn singles(&self) -> Option<Step> {
+        self.full_house()
+            .or_else(|| self.hidden_single())
+            .or_else(|| self.naked_single())
     }
 
-    fn hidden_singles(&mut self) -> bool {
-        let mut change = false; 
+    /// Sets with only one cell left to fill.
+    fn full_house(&self) -> Option<Step> {
+        for (set_index, set) in self.rules.sets.iter().enumerate() {
+            let unsolved = set & !self.cell_complete;
 
-        let mut masks = [u128::MAX; 9];
-        
-        for set in &self.rules.sets {
+            if unsolved.count_ones() == 1 {
+                let index = unsolved.trailing_zeros() as u8;
 
-            for (d, c) in self.candidates.iter().enumerate() {
-                let set_incomplete_candidates = *c & set & !self.cell_complete;
+                if let Some(digit) = self.candidates_vec(index).first() {
+                    let mut step = Step::new(Technique::FullHouse);
+                    step.sets.push(self.rules.describe_set(set_index));
+                    step.placements[*digit] = unsolved;
+                    return Some(step);
+                }
+            }
+        }
 
-                if set_incomplete_candidates.count_ones() == 1 { // we have a hidden single
+        None
+    }
 
-                    let idxs = get_set_indexes(set_incomplete_candidates);
-                    let idx = idxs.first().unwrap();
-                    
-                    for m in masks.iter_mut() {
-                        *m &= !(1 << idx); // remove all the candidates for this index
-                    }
+    /// Digits with only one place left in a set.
+    fn hidden_single(&self) -> Option<Step> {
+        for (set_index, set) in self.rules.sets.iter().enumerate() {
+            let set_candidates = self.candidates.iter().map(|x| x & set & !self.cell_complete).enumerate();
+            
+            let one_candidates = set_candidates.filter(|x| x.1.count_ones() == 1);
 
-                    masks[d] |= 1 << idx; // add back all other candidates
+            // find hidden singles, the naked ones are found below
+            for c in one_candidates {
+                let index = c.1.trailing_zeros() as u8;
 
-                    change = true;
+                if self.get_candidates(index).count_ones() > 1 {
+                    let mut step = Step::new(Technique::HiddenSingle);
+                    step.sets.push(self.rules.describe_set(set_index));
+                    step.placements[c.0] = c.1;
+                    return Some(step);
                 }
             }
         }
 
-        for (d, c) in self.candidates.iter_mut().enumerate() {
-            *c &= masks[d];
+        None
+    }
+
+    /// Cells with only one candidate left.
+    fn naked_single(&self) -> Option<Step> {
+        for index in 0..81 {
+            if !self.is_complete(index) && self.get_candidates(index).count_ones() == 1 {
+                let mut step = Step::new(Technique::NakedSingle);
+                step.placements[self.candidates_vec(index)[0]] = 1 << index;
+                return Some(step);
+            }
         }
 
-        change    
+        None
     }
 
+    /// The same board with different logic engine settings.
+    pub fn with_options(mut self, options: LogicOptions) -> BitmaskBoard {
+        self.options = options;
+        self
+    }
 
-    fn hidden_naked_singles_pairs_triples(&mut self) -> bool {
-        let mut changed = false;
+    /// A copy of the board that displays the two classes of cells in `colors` highlighted.
+    pub fn with_colors(&self, colors: [u128; 2]) -> BitmaskBoard {
+        let mut brd = self.clone();
+        brd.update_candidates();
+        brd.colors = Some(colors);
+        brd
+    }
 
-        let mut masks = [u128::MAX; 9];
+    /// Works out the candidates from the filled in cells and returns the single easiest next
+    /// deduction, leaving the board untouched.
+    pub fn hint(&self) -> Option<Step> {
+        let mut brd = self.clone();
+        brd.update_candidates();
 
-        for set in self.rules.sets.clone() {
-            let set_candidates = self.candidates.iter().map(|x| x & set).enumerate();
-            
-            let one_candidates   = set_candidates.clone().filter(|x| x.1.count_ones() == 1);
-            let two_candidates: Vec<(usize, u128)>   = set_candidates.clone().filter(|x| x.1.count_ones() == 2).collect();
-            let three_candidates: Vec<(usize, u128)> = set_candidates.clone().filter(|x| x.1.count_ones() == 3).collect();
+        if !brd.is_legal() {
+            return None;
+        }
 
-            // find hidden or naked singles
-            for c in one_candidates {
-                let eliminate = set ^ c.1;
-                let allow = !eliminate;
-                masks[c.0] &= allow;
-                changed = true;
-                // println!("eliminated {} from {}\n{}", c.0 + 1, pretty_print_bitmask(eliminate), self)
-            }
+        brd.next_step()
+    }
 
+    /// Runs the logic engine without ever guessing, returning every deduction it made in order.
+    /// Stops once the board is solved, illegal or logic gets stuck.
+    pub fn solve_logically(&mut self) -> Vec<Step> {
+        let mut steps = vec![];
 
-            // // find hidden or naked singles
-            // for (i, c) in 0..two_candidates.len() {
-            //     let eliminate = set ^ c.1;
-            //     let allow = !eliminate;
-            //     masks[c.0] &= allow;
-            //     changed = true;
-            //     // println!("eliminated {} from {}\n{}", c.0 + 1, pretty_print_bitmask(eliminate), self)
-            // }
-            
+        self.update_candidates();
 
+        while self.is_legal() && !self.is_solved() {
+            match self.optimize() {
+                Some(step) => {steps.push(step)},
+                None => {break},
+            }
         }
 
+        steps
+    }
+
 
-        for (d, c) in self.candidates.iter_mut().enumerate() {
-            *c &= masks[d];
+    /// Applies logic until nothing changes, returning false if the board turned out to be illegal.
+    fn propagate(&mut self) -> bool {
+        loop {
+            let before = self.clone();
+
+            self.update_cell_complete();
+            self.update_candidates();
+
+            if !self.is_legal() {
+                return false;
+            } else if self.is_solved() {
+                return true;
+            }
+
+            if before == *self {
+                self.optimize();
+
+                if before == *self {
+                    return true;
+                }
+            }
+        }
+    }
+
+    /// Lazily walks every completion of the board, stopping after `limit` of them if given.
+    pub fn solutions(&self, limit: Option<usize>) -> Solutions {
+        Solutions {
+            stack: vec![self.clone().with_options(self.search_options())],
+            remaining: limit,
         }
+    }
 
-        changed
+    /// Settings for the logic inside the guessing search. It has to find every solution, so it
+    /// can't assume there is only one, and it runs at every node, so it sticks to singles and
+    /// cage sums (which the cage constraints leave to the logic). The configured profile is for
+    /// `solve_logically`, `hint` and `grade`.
+    fn search_options(&self) -> LogicOptions {
+        let profile = Profile::from_techniques([Technique::FullHouse, Technique::HiddenSingle, Technique::NakedSingle, Technique::CageSum]);
+        LogicOptions {forcing_depth: None, assume_unique: false, profile}
     }
 
+    /// Solves the board, guessing once logic gets stuck. Only singles and cage sums are used
+    /// along the way, so the outcome never depends on `assume_unique`.
+    pub fn solve(&mut self, recursion_count: &mut u128, stop_if_bifurcate: bool) -> SolveOutcome {
+        let search_options = self.search_options();
+        let options = std::mem::replace(&mut self.options, search_options);
+        let outcome = self.search(recursion_count, stop_if_bifurcate);
+        self.options = options;
+
+        outcome
+    }
 
-    pub fn solve(&mut self, recursion_count: &mut u128, stop_if_bifurcate: bool) -> u128 {
+    fn search(&mut self, recursion_count: &mut u128, stop_if_bifurcate: bool) -> SolveOutcome {
 
         // println!("{}", self.short_string());
 
         if *recursion_count >= 10000 { // break out of this if it gets too crazy
-            return 3;
+            return SolveOutcome::Aborted {
+                reason: AbortReason::RecursionLimit,
+                stats: SolveStats {recursion_count: *recursion_count, iterations: 0},
+            };
         }
 
         let mut i = 0; 
@@ -682,22 +1059,18 @@ impl BitmaskBoard {
             let before = self.clone();
 
             if !self.is_legal() {
-                //dbg!(0);
-                return 0;
+                return SolveOutcome::NoSolution;
             } else if self.is_solved() {
-                //dbg!(1);
-                return 1;
+                return SolveOutcome::Unique(Box::new(self.clone()));
             } 
 
             self.update_cell_complete();
             self.update_candidates();
 
             if !self.is_legal() {
-                //dbg!(0);
-                return 0;
+                return SolveOutcome::NoSolution;
             } else if self.is_solved() {
-                //dbg!(1);
-                return 1;
+                return SolveOutcome::Unique(Box::new(self.clone()));
             } 
 
             // println!("{}", self.short_string());
@@ -710,47 +1083,39 @@ impl BitmaskBoard {
                 if before == *self {
 
                     if stop_if_bifurcate {
-                        return 2;
+                        return SolveOutcome::NeedsGuessing(Box::new(self.clone()));
                     }
 
-                    let (index, count) = self.find_lowest_candidates_unsolved();
+                    let (index, _) = self.find_lowest_candidates_unsolved();
                     let candidates = self.candidates_vec(index);
 
-                    let mut potential_solution: BitmaskBoard = BitmaskBoard::new(self.rules.clone()); 
-                    let mut solutions_found = 0;
+                    let mut first_solution: Option<Box<BitmaskBoard>> = None;
 
                     for c in &candidates {
 
-                        for count in 0..(candidates.len() as u128 - 1) {
-                            if recursion_count.count_ones() == 1 {
-                                //println!("Recursion: x{}", recursion_count)
-                            }
-                            *recursion_count += 1;
-                        }
+                        *recursion_count += candidates.len() as u128 - 1;
 
                         let mut new_sudoku = self.clone();
                         new_sudoku.set_cell(*c, index);
 
-                        let result = new_sudoku.solve(recursion_count, stop_if_bifurcate);
-
-                        solutions_found += result;
-
-                        // dbg!(result, solutions_found);
-
-                        if result == 1 {
-                            potential_solution = new_sudoku;
-                        } else if result > 1 || solutions_found > 1 { // if multiple legal solutions were found then return everything early
-                            return 2;
+                        match new_sudoku.search(recursion_count, stop_if_bifurcate) {
+                            SolveOutcome::NoSolution => {},
+                            SolveOutcome::Unique(solution) => {
+                                match first_solution {
+                                    // two different branches worked out, so we can stop looking
+                                    Some(first) => {return SolveOutcome::Multiple {first, second: solution}},
+                                    None => {first_solution = Some(solution)},
+                                }
+                            },
+                            // if multiple legal solutions were found (or we gave up) then return everything early
+                            other => {return other},
                         }
                     }
 
-                    match solutions_found {
-                        0 => {return 0},
-                        1 => {*self = potential_solution;return 1}
-                        _ => {return 2}
+                    match first_solution {
+                        None => {return SolveOutcome::NoSolution},
+                        Some(solution) => {*self = (*solution).clone(); return SolveOutcome::Unique(solution)},
                     }
-                    
-
 
                 }
             }
@@ -760,7 +1125,93 @@ impl BitmaskBoard {
         
         }
 
-        panic!("Too many iterations")
+        SolveOutcome::Aborted {
+            reason: AbortReason::TooManyIterations,
+            stats: SolveStats {recursion_count: *recursion_count, iterations: i},
+        }
     }
 
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normal_rules() -> Rules {
+        Rules {
+            normal_sudoku: true,
+            rubiks: false,
+            diagonals: false,
+            windoku: false,
+            sets: vec![],
+            regions: vec![],
+            cages: vec![],
+            anti_knight: false,
+            anti_king: false,
+            anti_queen: 0,
+        }
+    }
+
+    #[test]
+    fn solves_killer_with_cage_sums() {
+        let mut brd = BitmaskBoard::from_file(Path::new("test_sudoku/killer.sudoku"), normal_rules()).unwrap();
+
+        assert_eq!(brd.solutions(Some(2)).count(), 1);
+        assert!(matches!(brd.solve(&mut 0, false), SolveOutcome::Unique(_)));
+    }
+
+    #[test]
+    fn solves_jigsaw_with_its_regions() {
+        let mut brd = BitmaskBoard::from_file(Path::new("test_sudoku/jigsaw.sudoku"), normal_rules()).unwrap();
+
+        assert_eq!(brd.rules.regions.len(), 9);
+        assert_eq!(brd.solutions(Some(2)).count(), 1);
+        assert!(matches!(brd.solve(&mut 0, false), SolveOutcome::Unique(_)));
+    }
+
+    #[test]
+    fn default_profile_goes_from_easiest_to_hardest() {
+        let ratings: Vec<f32> = Profile::default().techniques.iter().map(|(technique, _)| technique.rating()).collect();
+
+        assert!(ratings.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn grades_by_easiest_technique_that_works() {
+        // a Two-String Kite also works here, but the cheaper Sashimi X-Wing comes first
+        let puzzle = "1.25........6.7...7...8...54.1....79....2..6..9.......58......1..4..1..3.....42..";
+        let grade = BitmaskBoard::from_string(puzzle.to_string(), normal_rules()).unwrap().grade();
+
+        assert_eq!(grade.hardest, Some(Technique::SashimiFish(2)));
+        assert_eq!(grade.category, Category::Medium);
+        assert!(grade.solved);
+    }
+
+    #[test]
+    fn finds_mutant_fish_mixing_rows_and_columns() {
+        let puzzle = ".........1..8....96.8.2..3.59.7.2..12..3..7.............1...65..8..63.4.7.6..5...";
+        let profile = Profile::parse("Full House, Hidden Single, Naked Single, Locked Candidates, Mutant X-Wing, Mutant Swordfish").unwrap();
+        let options = LogicOptions {profile, ..LogicOptions::default()};
+
+        let brd = BitmaskBoard::from_string(puzzle.to_string(), normal_rules()).unwrap().with_options(options);
+        let solution = brd.solutions(Some(1)).next().unwrap();
+
+        let steps = brd.clone().solve_logically();
+        let mutant = steps.iter().find(|step| step.technique == Technique::MutantFish(2)).unwrap();
+
+        assert!(mutant.detail.starts_with("1 base {row 8, column 8}"));
+
+        for step in &steps {
+            assert!((0..9).all(|digit| step.eliminations[digit] & solution.candidates[digit] == 0), "{} is wrong", step);
+        }
+    }
+
+    #[test]
+    fn ignores_rubiks_colours_without_rubiks_rules() {
+        let brd = BitmaskBoard::from_file(Path::new("test_sudoku/rubiks.sudoku"), normal_rules()).unwrap();
+
+        assert!(brd.rules.regions.is_empty());
+        assert!(brd.rules.sets.contains(&box_set(0)));
+    }
+}
@@ -0,0 +1,292 @@
+use crate::rules::{box_set, column_set, describe_cell, row_set};
+use super::{BitmaskBoard, combinations, get_set_indexes};
+use super::steps::{describe_digits, Step, Technique};
+
+/// An almost locked set: some cells of one set with one more digit between them than cells.
+#[derive(Debug, Clone, Copy)]
+struct Als {
+    cells: u128,
+    digits: u16,
+    /// Per digit, the cells of the ALS that have it.
+    digit_cells: [u128; 9],
+    /// Per digit, the cells that see every cell of the ALS with that digit.
+    seen: [u128; 9],
+}
+
+impl Als {
+    fn has(&self, digit: usize) -> bool {
+        self.digits & (1 << digit) != 0
+    }
+}
+
+fn describe_als(als: &Als) -> String {
+    let cells: Vec<String> = get_set_indexes(als.cells).into_iter().map(describe_cell).collect();
+    format!("{{{}}} {}", cells.join(", "), describe_digits(als.digits))
+}
+
+impl BitmaskBoard {
+
+    /// Every ALS in any of the sets, each group of cells only once.
+    fn almost_locked_sets(&self) -> Vec<Als> {
+        let unsolved = !self.cell_complete & 0x1FFFFFFFFFFFFFFFFFFFF;
+        let mut found: Vec<u128> = vec![];
+        let mut sets = vec![];
+
+        for set in &self.rules.sets {
+            let cells = get_set_indexes(set & unsolved);
+
+            for size in 1..cells.len() {
+                for subset in combinations(&cells, size) {
+                    let mask = subset.iter().fold(0u128, |acc, i| acc | (1 << i));
+                    let digits = subset.iter().fold(0, |acc, i| acc | self.get_candidates(*i));
+
+                    if digits.count_ones() as usize != size + 1 || found.contains(&mask) {
+                        continue;
+                    }
+
+                    found.push(mask);
+
+                    let mut als = Als {cells: mask, digits, digit_cells: [0; 9], seen: [0; 9]};
+                    for digit in 0..9 {
+                        als.digit_cells[digit] = self.candidates[digit] & mask;
+                        als.seen[digit] = if als.has(digit) {self.seen_by_all(als.digit_cells[digit])} else {0};
+                    }
+
+                    sets.push(als);
+                }
+            }
+        }
+
+        sets
+    }
+
+    /// Whether `digit` is a restricted common candidate of two ALS: both have it and every cell
+    /// with it in one sees every cell with it in the other, so at most one of them can hold it.
+    fn restricted_common(a: &Als, b: &Als, digit: usize) -> bool {
+        a.has(digit) && b.has(digit) && a.digit_cells[digit] & !b.seen[digit] == 0
+    }
+
+    /// Cells that can't be `digit` when one of two ALS is certain to hold it.
+    fn als_eliminations(&self, a: &Als, b: &Als, digit: usize) -> u128 {
+        self.candidates[digit] & !self.cell_complete & a.seen[digit] & b.seen[digit] & !(a.cells | b.cells)
+    }
+
+    /// Two ALS joined by a restricted common candidate x. At most one of them holds x, so the
+    /// other is locked without it and holds every other digit it has, including any common z.
+    pub(super) fn als_xz(&self) -> Option<Step> {
+        let sets = self.almost_locked_sets();
+
+        for (i, a) in sets.iter().enumerate() {
+            for b in sets.iter().skip(i + 1) {
+                if a.cells & b.cells != 0 || a.digits & b.digits == 0 {
+                    continue;
+                }
+
+                for x in (0..9).filter(|x| Self::restricted_common(a, b, *x)) {
+                    let mut step = Step::new(Technique::ALSXZ);
+
+                    for z in (0..9).filter(|z| *z != x && a.has(*z) && b.has(*z)) {
+                        step.eliminations[z] = self.als_eliminations(a, b, z);
+                    }
+
+                    if !step.is_empty() {
+                        step.detail = format!("x={} {} & {}", x + 1, describe_als(a), describe_als(b));
+                        return Some(step);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// A pivot ALS restricted to one ALS by x and another by y. If the pivot can't have both,
+    /// one of the wings has to be locked, so a z common to the wings is in one of them.
+    pub(super) fn als_xy_wing(&self) -> Option<Step> {
+        let sets = self.almost_locked_sets();
+
+        for pivot in &sets {
+            let mut wings = vec![];
+
+            for other in &sets {
+                if other.cells & pivot.cells != 0 {
+                    continue;
+                }
+
+                for x in (0..9).filter(|x| Self::restricted_common(pivot, other, *x)) {
+                    wings.push((other, x));
+                }
+            }
+
+            for (i, (a, x)) in wings.iter().enumerate() {
+                for (b, y) in wings.iter().skip(i + 1) {
+                    if x == y || a.cells & b.cells != 0 {
+                        continue;
+                    }
+
+                    let mut step = Step::new(Technique::ALSXYWing);
+
+                    for z in (0..9).filter(|z| z != x && z != y && a.has(*z) && b.has(*z)) {
+                        step.eliminations[z] = self.als_eliminations(a, b, z) & !pivot.cells;
+                    }
+
+                    if !step.is_empty() {
+                        step.detail = format!("x={} y={} pivot {} wings {} & {}", x + 1, y + 1, describe_als(pivot), describe_als(a), describe_als(b));
+                        return Some(step);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// A stem cell with a petal ALS for each of its candidates, where every cell of the petal
+    /// with that candidate sees the stem. Whichever digit the stem is, that petal is locked, so
+    /// a digit all the petals have is in one of them.
+    pub(super) fn death_blossom(&self) -> Option<Step> {
+        let sets = self.almost_locked_sets();
+        let unsolved = !self.cell_complete & 0x1FFFFFFFFFFFFFFFFFFFF;
+
+        for stem in get_set_indexes(unsolved) {
+            let stem_digits = self.candidates_vec(stem);
+
+            if !(2..=3).contains(&stem_digits.len()) {
+                continue;
+            }
+
+            let petals: Vec<Vec<&Als>> = stem_digits.iter()
+                .map(|d| sets.iter()
+                    .filter(|als| als.cells & (1 << stem) == 0 && als.has(*d) && als.digit_cells[*d] & !self.peers(stem) == 0)
+                    .collect())
+                .collect();
+
+            let mut chosen = vec![];
+            let common = 0x1FF & !self.get_candidates(stem);
+
+            if let Some(eliminations) = self.blossom_search(&petals, &mut chosen, common, 1 << stem) {
+                let petals: Vec<String> = stem_digits.iter().zip(chosen.iter())
+                    .map(|(d, als)| format!("{}: {}", d + 1, describe_als(als)))
+                    .collect();
+
+                let mut step = Step::new(Technique::DeathBlossom);
+                step.detail = format!("stem {} petals {}", describe_cell(stem), petals.join(", "));
+                step.eliminations = eliminations;
+                return Some(step);
+            }
+        }
+
+        None
+    }
+
+    /// Picks one petal per stem digit, keeping them apart and keeping track of the digits they
+    /// all share, and returns what the first complete blossom eliminates.
+    fn blossom_search<'a>(&self, petals: &[Vec<&'a Als>], chosen: &mut Vec<&'a Als>, common: u16, used: u128) -> Option<[u128; 9]> {
+        if common == 0 {
+            return None;
+        }
+
+        if chosen.len() == petals.len() {
+            let mut eliminations = [0; 9];
+
+            for z in (0..9).filter(|z| common & (1 << z) != 0) {
+                eliminations[z] = chosen.iter().fold(self.candidates[z] & !self.cell_complete & !used, |acc, als| acc & als.seen[z]);
+            }
+
+            return if eliminations.iter().any(|m| *m != 0) {Some(eliminations)} else {None};
+        }
+
+        for als in petals[chosen.len()].iter().copied() {
+            if als.cells & used != 0 {
+                continue;
+            }
+
+            chosen.push(als);
+            if let Some(eliminations) = self.blossom_search(petals, chosen, common & als.digits, used | als.cells) {
+                return Some(eliminations);
+            }
+            chosen.pop();
+        }
+
+        None
+    }
+
+    /// Two or three cells where a box meets a line, with at least two more digits than cells.
+    /// Some cells in the rest of the line and some in the rest of the box, with no digits in
+    /// common, take up the extra digits, so the line's digits can go from the rest of the line
+    /// and the box's digits from the rest of the box.
+    pub(super) fn sue_de_coq(&self) -> Option<Step> {
+        // leans on boxes and lines being sets
+        if !self.rules.has_boxes() {
+            return None;
+        }
+
+        let unsolved = !self.cell_complete & 0x1FFFFFFFFFFFFFFFFFFFF;
+        let position = |set: u128| self.rules.sets.iter().position(|s| *s == set).unwrap();
+        let digits_of = |cells: &[u8]| cells.iter().fold(0u16, |acc, i| acc | self.get_candidates(*i));
+
+        for sudoku_box in 0..9 {
+            let box_mask = box_set(sudoku_box);
+
+            for line in (0..9).map(row_set).chain((0..9).map(column_set)) {
+                let intersection = get_set_indexes(box_mask & line & unsolved);
+
+                for size in 2..=intersection.len() {
+                    for core in combinations(&intersection, size) {
+                        let core_digits = digits_of(&core);
+
+                        if (core_digits.count_ones() as usize) < size + 2 {
+                            continue;
+                        }
+
+                        let useful = |i: &u8| self.get_candidates(*i) & core_digits != 0;
+                        let line_rest: Vec<u8> = get_set_indexes(line & !box_mask & unsolved).into_iter().filter(useful).collect();
+                        let box_rest: Vec<u8> = get_set_indexes(box_mask & !line & unsolved).into_iter().filter(useful).collect();
+
+                        for line_size in 1..=2 {
+                            for line_cells in combinations(&line_rest, line_size) {
+                                let line_digits = digits_of(&line_cells);
+
+                                for box_size in 1..=2 {
+                                    for box_cells in combinations(&box_rest, box_size) {
+                                        let box_digits = digits_of(&box_cells);
+                                        let all_digits = core_digits | line_digits | box_digits;
+
+                                        if line_digits & box_digits != 0 || all_digits.count_ones() as usize != size + line_size + box_size {
+                                            continue;
+                                        }
+
+                                        let mask = |cells: &[u8]| cells.iter().fold(0u128, |acc, i| acc | (1 << i));
+                                        let line_targets = line & unsolved & !mask(&core) & !mask(&line_cells);
+                                        let box_targets = box_mask & unsolved & !mask(&core) & !mask(&box_cells);
+
+                                        let mut step = Step::new(Technique::SueDeCoq);
+
+                                        for digit in (0..9).filter(|d| all_digits & (1 << d) != 0) {
+                                            if box_digits & (1 << digit) == 0 {
+                                                step.eliminations[digit] |= self.candidates[digit] & line_targets;
+                                            }
+                                            if line_digits & (1 << digit) == 0 {
+                                                step.eliminations[digit] |= self.candidates[digit] & box_targets;
+                                            }
+                                        }
+
+                                        if !step.is_empty() {
+                                            let describe = |cells: &[u8]| cells.iter().map(|i| describe_cell(*i)).collect::<Vec<String>>().join(", ");
+                                            step.detail = format!("{{{}}} {} with {{{}}} and {{{}}}", describe(&core), describe_digits(core_digits), describe(&line_cells), describe(&box_cells));
+                                            step.sets.push(self.rules.describe_set(position(box_mask)));
+                                            step.sets.push(self.rules.describe_set(position(line)));
+                                            return Some(step);
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
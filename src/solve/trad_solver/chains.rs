@@ -0,0 +1,270 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::rules::{box_set, column_set, row_set};
+use super::{BitmaskBoard, get_set_indexes};
+use super::steps::{Step, Technique};
+
+/// A digit in one cell, or in any of a few cells that fill a box/line intersection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Node {
+    digit: usize,
+    cells: u128,
+}
+
+/// Names a single cell or a box/line group the Eureka way, e.g. `r1c5` or `r1c456`.
+fn describe_cells(cells: u128) -> String {
+    let indexes = get_set_indexes(cells);
+    let mut rows: Vec<u8> = indexes.iter().map(|i| i / 9 + 1).collect();
+    let mut columns: Vec<u8> = indexes.iter().map(|i| i % 9 + 1).collect();
+    rows.dedup();
+    columns.sort();
+    columns.dedup();
+
+    let join = |v: Vec<u8>| v.iter().map(|x| x.to_string()).collect::<String>();
+    format!("r{}c{}", join(rows), join(columns))
+}
+
+/// Eureka notation for a chain that starts on a strong link, e.g. `(1)r1c1=(1)r1c5-(1=2)r3c5`.
+fn describe_chain(nodes: &[Node]) -> String {
+    let symbol = |link: usize| if link.is_multiple_of(2) {'='} else {'-'};
+    let mut out = String::new();
+    let mut i = 0;
+
+    while i < nodes.len() {
+        let mut j = i;
+        while j + 1 < nodes.len() && nodes[j + 1].cells == nodes[i].cells {
+            j += 1;
+        }
+
+        out.push('(');
+        for (t, node) in nodes[i..=j].iter().enumerate() {
+            if t > 0 {
+                out.push(symbol(i + t - 1));
+            }
+            out.push_str(&(node.digit + 1).to_string());
+        }
+        out.push(')');
+        out.push_str(&describe_cells(nodes[i].cells));
+
+        if j + 1 < nodes.len() {
+            out.push(symbol(j));
+        }
+
+        i = j + 1;
+    }
+
+    out
+}
+
+impl BitmaskBoard {
+
+    fn chain_nodes(&self, grouped: bool) -> Vec<Node> {
+        let mut nodes = vec![];
+
+        for digit in 0..9 {
+            let candidates = self.candidates[digit] & !self.cell_complete;
+
+            for index in get_set_indexes(candidates) {
+                nodes.push(Node {digit, cells: 1 << index});
+            }
+
+            // box/line groups only make sense when there are boxes and lines
+            if grouped && self.rules.has_boxes() {
+                for sudoku_box in 0..9 {
+                    for line in 0..9 {
+                        for line_set in [row_set(line), column_set(line)] {
+                            let group = candidates & box_set(sudoku_box) & line_set;
+
+                            if group.count_ones() >= 2 {
+                                nodes.push(Node {digit, cells: group});
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        nodes
+    }
+
+    /// Strong and weak links out of every node, as indexes into `nodes`. `technique` decides which
+    /// kinds of link are allowed: X-Chains only link a digit across cells, XY-Chains have all
+    /// their strong links inside bivalue cells and all their weak links across cells.
+    fn chain_links(&self, nodes: &[Node], technique: Technique) -> (Vec<Vec<usize>>, Vec<Vec<usize>>) {
+        let lookup: HashMap<Node, usize> = nodes.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+
+        let (set_strong, cell_strong, cell_weak) = match technique {
+            Technique::XChain => (true, false, false),
+            Technique::XYChain => (false, true, false),
+            _ => (true, true, true),
+        };
+
+        let mut strong = vec![vec![]; nodes.len()];
+        let mut weak = vec![vec![]; nodes.len()];
+
+        for (a_index, a) in nodes.iter().enumerate() {
+            let candidates = self.candidates[a.digit] & !self.cell_complete;
+
+            if set_strong {
+                for set in &self.rules.sets {
+                    if a.cells & !set != 0 {
+                        continue;
+                    }
+
+                    let rest = candidates & set & !a.cells;
+
+                    if let Some(b_index) = lookup.get(&Node {digit: a.digit, cells: rest}) {
+                        if !strong[a_index].contains(b_index) {
+                            strong[a_index].push(*b_index);
+                        }
+                    }
+                }
+            }
+
+            let seen = self.seen_by_all(a.cells);
+
+            for (b_index, b) in nodes.iter().enumerate() {
+                if b.digit == a.digit && b.cells & a.cells == 0 && b.cells & !seen == 0 {
+                    weak[a_index].push(b_index);
+                }
+            }
+
+            if a.cells.count_ones() == 1 {
+                let index = a.cells.trailing_zeros() as u8;
+                let digits = self.get_candidates(index);
+
+                for digit in (0..9).filter(|d| *d != a.digit && digits & (1 << d) != 0) {
+                    let b_index = lookup[&Node {digit, cells: a.cells}];
+
+                    if cell_strong && digits.count_ones() == 2 {
+                        strong[a_index].push(b_index);
+                    }
+
+                    if cell_weak {
+                        weak[a_index].push(b_index);
+                    }
+                }
+            }
+        }
+
+        (strong, weak)
+    }
+
+    /// What can go given that at least one of two nodes is true.
+    fn chain_eliminations(&self, first: Node, last: Node) -> [u128; 9] {
+        let mut eliminations = [0; 9];
+        let both = first.cells | last.cells;
+
+        if first.digit == last.digit {
+            eliminations[first.digit] = self.candidates[first.digit] & !self.cell_complete
+                & self.seen_by_all(first.cells) & self.seen_by_all(last.cells) & !both;
+            return eliminations;
+        }
+
+        if first.cells == last.cells && first.cells.count_ones() == 1 {
+            // one cell that has to be one of two digits
+            for (digit, mask) in eliminations.iter_mut().enumerate() {
+                if digit != first.digit && digit != last.digit {
+                    *mask = self.candidates[digit] & first.cells;
+                }
+            }
+            return eliminations;
+        }
+
+        // a single cell that sees the whole other end can't hold the other end's digit
+        if last.cells.count_ones() == 1 && last.cells & !self.seen_by_all(first.cells) == 0 {
+            eliminations[first.digit] |= self.candidates[first.digit] & last.cells;
+        }
+
+        if first.cells.count_ones() == 1 && first.cells & !self.seen_by_all(last.cells) == 0 {
+            eliminations[last.digit] |= self.candidates[last.digit] & first.cells;
+        }
+
+        eliminations
+    }
+
+    /// Alternating inference chains: starting from a node assumed false and following strong
+    /// and weak links in turn, every node reached over a strong link is true. So either the
+    /// first node or any such node is true, and anything clashing with both goes. A chain that
+    /// gets back to its own first node that way proves it true. Returns the shortest chain that
+    /// does anything.
+    pub(super) fn chain(&self, technique: Technique) -> Option<Step> {
+        let nodes = self.chain_nodes(technique == Technique::GroupedAIC);
+        let (strong, weak) = self.chain_links(&nodes, technique);
+
+        let mut best: Option<(Vec<usize>, Step)> = None;
+
+        for start in 0..nodes.len() {
+            if strong[start].is_empty() {
+                continue;
+            }
+
+            // states are a node and whether it was reached as true (over a strong link)
+            let mut parent: HashMap<(usize, bool), (usize, bool)> = HashMap::new();
+            let mut queue = VecDeque::from([((start, false), 0)]);
+            parent.insert((start, false), (start, false));
+
+            while let Some(((node, on), length)) = queue.pop_front() {
+                if best.as_ref().is_some_and(|(chain, _)| length + 2 >= chain.len()) {
+                    break;
+                }
+
+                let next = if on {&weak[node]} else {&strong[node]};
+
+                for other in next.iter().copied() {
+                    let state = (other, !on);
+
+                    if parent.contains_key(&state) {
+                        continue;
+                    }
+
+                    let mut step = Step::new(technique);
+
+                    if other == start {
+                        // the chain came back round and proved its own start true
+                        if !state.1 || nodes[start].cells.count_ones() != 1 {
+                            continue;
+                        }
+
+                        step.placements[nodes[start].digit] = nodes[start].cells;
+                    } else {
+                        parent.insert(state, (node, on));
+                        queue.push_back((state, length + 1));
+
+                        if !state.1 {
+                            continue;
+                        }
+
+                        step.eliminations = self.chain_eliminations(nodes[start], nodes[other]);
+                    }
+
+                    if step.is_empty() {
+                        continue;
+                    }
+
+                    let mut chain = vec![other];
+                    let mut current = (node, on);
+                    chain.push(node);
+                    while current != (start, false) {
+                        current = parent[&current];
+                        chain.push(current.0);
+                    }
+                    chain.reverse();
+
+                    let grouped_chain = chain.iter().any(|i| nodes[*i].cells.count_ones() > 1);
+
+                    if (technique != Technique::GroupedAIC || grouped_chain)
+                        && best.as_ref().is_none_or(|(b, _)| chain.len() < b.len()) {
+                        best = Some((chain, step));
+                    }
+                }
+            }
+        }
+
+        let (chain, mut step) = best?;
+        let chain: Vec<Node> = chain.into_iter().map(|i| nodes[i]).collect();
+
+        step.detail = describe_chain(&chain);
+        Some(step)
+    }
+}
@@ -0,0 +1,123 @@
+use super::{BitmaskBoard, get_set_indexes};
+use super::steps::{Step, Technique};
+
+impl BitmaskBoard {
+
+    /// Every cell that sees at least one of the cells in `cells`.
+    fn seen_by_any(&self, cells: u128) -> u128 {
+        get_set_indexes(cells).into_iter().fold(0, |acc, i| acc | self.peers(i))
+    }
+
+    /// Splits the strong link graph of a digit into connected clusters and colours each one in
+    /// two: along a strong link the colour flips, so exactly one colour of a cluster is the digit.
+    fn color_clusters(&self, digit: usize) -> Vec<[u128; 2]> {
+        let links = self.strong_links(digit);
+        let mut colored = 0;
+        let mut clusters = vec![];
+
+        for start in links.iter().map(|link| link.ends.0) {
+            if colored & (1 << start) != 0 {
+                continue;
+            }
+
+            let mut cluster = [1 << start, 0];
+            let mut queue = vec![(start, 0)];
+
+            while let Some((cell, color)) = queue.pop() {
+                for link in &links {
+                    let other = match link.ends {
+                        (a, b) if a == cell => b,
+                        (a, b) if b == cell => a,
+                        _ => continue,
+                    };
+
+                    if (cluster[0] | cluster[1]) & (1 << other) == 0 {
+                        cluster[1 - color] |= 1 << other;
+                        queue.push((other, 1 - color));
+                    }
+                }
+            }
+
+            colored |= cluster[0] | cluster[1];
+            clusters.push(cluster);
+        }
+
+        clusters
+    }
+
+    fn coloring_step(&self, technique: Technique, digit: usize, colors: [u128; 2], eliminate: u128) -> Step {
+        let mut step = Step::new(technique);
+        step.detail = (digit + 1).to_string();
+        step.eliminations[digit] = eliminate;
+        step.colors = Some(colors);
+        step
+    }
+
+    /// Colour traps (a cell sees both colours of a cluster, so it can't be the digit) and colour
+    /// wraps (two cells of the same colour see each other, so that whole colour is false).
+    pub(super) fn simple_coloring(&self, technique: Technique) -> Option<Step> {
+        for digit in 0..9 {
+            let candidates = self.candidates[digit] & !self.cell_complete;
+
+            for cluster in self.color_clusters(digit) {
+                let seen = [self.seen_by_any(cluster[0]), self.seen_by_any(cluster[1])];
+
+                let eliminate = match technique {
+                    Technique::SimpleColorsTrap => candidates & seen[0] & seen[1] & !(cluster[0] | cluster[1]),
+                    Technique::SimpleColorsWrap => (0..2).filter(|c| cluster[*c] & seen[*c] != 0).fold(0, |acc, c| acc | cluster[c]),
+                    _ => unreachable!("only simple colors traps and wraps are looked for"),
+                };
+
+                if eliminate != 0 {
+                    return Some(self.coloring_step(technique, digit, cluster, eliminate));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Two clusters of the same digit where a colour of one sees a colour of the other, so those
+    /// two colours can't both be true. Type 1 removes anything seeing both of the other colours,
+    /// type 2 removes a colour that sees both colours of the other cluster.
+    pub(super) fn multi_coloring(&self, kind: usize) -> Option<Step> {
+        for digit in 0..9 {
+            let candidates = self.candidates[digit] & !self.cell_complete;
+            let clusters = self.color_clusters(digit);
+
+            for (a_index, a) in clusters.iter().enumerate() {
+                for (b_index, b) in clusters.iter().enumerate() {
+                    if a_index == b_index {
+                        continue;
+                    }
+
+                    let seen_b = [self.seen_by_any(b[0]), self.seen_by_any(b[1])];
+
+                    for i in 0..2 {
+                        for j in 0..2 {
+                            if a[i] & seen_b[j] == 0 {
+                                continue;
+                            }
+
+                            let (colors, eliminate) = match kind {
+                                1 => {
+                                    let clustered = a[0] | a[1] | b[0] | b[1];
+                                    ([a[i] | b[j], a[1 - i] | b[1 - j]], candidates & self.seen_by_any(a[1 - i]) & seen_b[1 - j] & !clustered)
+                                },
+                                2 if a[i] & seen_b[1 - j] != 0 => ([a[i], b[0] | b[1]], a[i]),
+                                2 => continue,
+                                _ => unreachable!("only multi colors types 1 and 2 are looked for"),
+                            };
+
+                            if eliminate != 0 {
+                                return Some(self.coloring_step(Technique::MultiColors(kind), digit, colors, eliminate));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,262 @@
+use crate::rules::{box_set, column_set, describe_cell, row_set};
+use super::{BitmaskBoard, combinations, get_set_indexes};
+use super::steps::{Step, Technique};
+
+struct Fish {
+    base: Vec<usize>,
+    cover: Vec<usize>,
+    fins: u128,
+    eliminate: u128,
+}
+
+impl BitmaskBoard {
+
+    /// Indexes into `rules.sets` of the given sets, skipping any the rules don't use.
+    fn set_indexes_of(&self, sets: &[u128]) -> Vec<usize> {
+        sets.iter()
+            .filter_map(|set| self.rules.sets.iter().position(|s| s == set))
+            .collect()
+    }
+
+    pub(super) fn describe_fish(&self, digit: usize, base: &[usize], cover: &[usize]) -> String {
+        let base: Vec<String> = base.iter().map(|i| self.rules.describe_set(*i)).collect();
+        let cover: Vec<String> = cover.iter().map(|i| self.rules.describe_set(*i)).collect();
+
+        format!("{} base {{{}}} cover {{{}}}", digit + 1, base.join(", "), cover.join(", "))
+    }
+
+    /// X-Wing, Swordfish and Jellyfish: `size` rows where a digit is confined to the same `size`
+    /// columns (or the other way round), so the digit can't go anywhere else in those columns.
+    pub(super) fn basic_fish(&self, size: usize) -> Option<Step> {
+        let technique = match size {
+            2 => Technique::XWing,
+            3 => Technique::Swordfish,
+            4 => Technique::Jellyfish,
+            _ => unreachable!("only fish of size 2 to 4 are looked for"),
+        };
+
+        let rows = self.set_indexes_of(&(0..9).map(row_set).collect::<Vec<u128>>());
+        let columns = self.set_indexes_of(&(0..9).map(column_set).collect::<Vec<u128>>());
+
+        for (base_lines, cover_lines) in [(&rows, &columns), (&columns, &rows)] {
+            for digit in 0..9 {
+                let candidates = self.candidates[digit] & !self.cell_complete;
+
+                let possible_base: Vec<usize> = base_lines.iter()
+                    .copied()
+                    .filter(|i| (1..=size).contains(&((candidates & self.rules.sets[*i]).count_ones() as usize)))
+                    .collect();
+
+                for base in combinations(&possible_base, size) {
+                    let base_mask = base.iter().fold(0, |acc, i| acc | self.rules.sets[*i]);
+                    let fish_candidates = candidates & base_mask;
+
+                    let cover: Vec<usize> = cover_lines.iter()
+                        .copied()
+                        .filter(|i| self.rules.sets[*i] & fish_candidates != 0)
+                        .collect();
+
+                    if cover.len() != size {
+                        continue;
+                    }
+
+                    let cover_mask = cover.iter().fold(0, |acc, i| acc | self.rules.sets[*i]);
+                    let eliminate = candidates & cover_mask & !base_mask;
+
+                    if eliminate != 0 {
+                        let mut step = Step::new(technique);
+                        step.detail = self.describe_fish(digit, &base, &cover);
+                        step.eliminations[digit] = eliminate;
+                        return Some(step);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Looks for a fish on `digit` with `size` base sets from `base_pool` and as many cover sets
+    /// from `cover_pool`. The base sets can't share candidates; the digit appears exactly once in
+    /// each of them, so it also appears exactly once in each cover set. With fins, only cells that
+    /// see every fin can be eliminated from. `kind` names a fish from its base, cover and fins,
+    /// and fish it gives no technique for are skipped so the search can carry on past them.
+    fn fish_search(&self, digit: usize, size: usize, base_pool: &[usize], cover_pool: &[usize], allow_fins: bool, kind: impl Fn(&[usize], &[usize], u128) -> Option<Technique>) -> Option<(Technique, Fish)> {
+        let candidates = self.candidates[digit] & !self.cell_complete;
+        let sets = &self.rules.sets;
+
+        let base_pool: Vec<usize> = base_pool.iter()
+            .copied()
+            .filter(|i| sets[*i] & candidates != 0)
+            .collect();
+
+        for base in combinations(&base_pool, size) {
+            let base_mask = base.iter().fold(0, |acc, i| acc | (sets[*i] & candidates));
+            let overlapping = base.iter().map(|i| (sets[*i] & candidates).count_ones()).sum::<u32>() != base_mask.count_ones();
+
+            if overlapping {
+                continue;
+            }
+
+            let cover_options: Vec<usize> = cover_pool.iter()
+                .copied()
+                .filter(|i| !base.contains(i) && sets[*i] & base_mask != 0)
+                .collect();
+
+            let max_fins = if allow_fins {4} else {0};
+            let mut cover = vec![];
+
+            let accept = |cover: &[usize], fins: u128| kind(&base, cover, fins).is_some();
+
+            if let Some((fins, eliminate)) = self.fish_cover_search(candidates, base_mask, base_mask, 0, u128::MAX, &cover_options, &mut cover, size, max_fins, &accept) {
+                let technique = kind(&base, &cover, fins)?;
+                return Some((technique, Fish {base, cover, fins, eliminate}));
+            }
+        }
+
+        None
+    }
+
+    /// Picks cover sets for a fish one at a time, always dealing with the lowest base candidate
+    /// that isn't covered yet: either a cover set takes it or it becomes a fin. Fills `cover` and
+    /// returns the fins and eliminations of the first fish that eliminates anything and that
+    /// `accept` takes, given the cover and fins.
+    #[allow(clippy::too_many_arguments)]
+    fn fish_cover_search(&self, candidates: u128, base_mask: u128, uncovered: u128, fins: u128, seen_by_fins: u128, options: &[usize], cover: &mut Vec<usize>, size: usize, max_fins: u32, accept: &dyn Fn(&[usize], u128) -> bool) -> Option<(u128, u128)> {
+        let sets = &self.rules.sets;
+
+        // nothing left that the fins could possibly eliminate
+        if fins != 0 && candidates & !base_mask & seen_by_fins == 0 {
+            return None;
+        }
+
+        if cover.len() == size {
+            if uncovered != 0 {
+                return None;
+            }
+
+            let cover_mask = cover.iter().fold(0, |acc, i| acc | sets[*i]);
+
+            let eliminate = if fins == 0 {
+                // base candidates in two cover sets at once would fill both, so they can't be true either
+                let mut cannibals = 0;
+                for pair in combinations(cover, 2) {
+                    cannibals |= sets[pair[0]] & sets[pair[1]] & base_mask;
+                }
+
+                (candidates & cover_mask & !base_mask) | cannibals
+            } else {
+                candidates & cover_mask & !base_mask & seen_by_fins
+            };
+
+            return if eliminate != 0 && accept(cover, fins) {Some((fins, eliminate))} else {None};
+        }
+
+        // the base is already inside fewer cover sets than it has base sets, which can only happen
+        // on a broken board, so there's no fish here
+        if uncovered == 0 {
+            return None;
+        }
+
+        let lowest = uncovered & uncovered.wrapping_neg();
+
+        for option in options.iter().copied() {
+            if cover.contains(&option) || sets[option] & lowest == 0 {
+                continue;
+            }
+
+            cover.push(option);
+            if let Some(found) = self.fish_cover_search(candidates, base_mask, uncovered & !sets[option], fins, seen_by_fins, options, cover, size, max_fins, accept) {
+                return Some(found);
+            }
+            cover.pop();
+        }
+
+        if fins.count_ones() < max_fins {
+            let seen_by_fins = seen_by_fins & self.peers(lowest.trailing_zeros() as u8);
+            return self.fish_cover_search(candidates, base_mask, uncovered & !lowest, fins | lowest, seen_by_fins, options, cover, size, max_fins, accept);
+        }
+
+        None
+    }
+
+    fn fish_step(&self, technique: Technique, digit: usize, fish: Fish) -> Step {
+        let mut step = Step::new(technique);
+        step.detail = self.describe_fish(digit, &fish.base, &fish.cover);
+
+        if fish.fins != 0 {
+            let fins: Vec<String> = get_set_indexes(fish.fins).into_iter().map(describe_cell).collect();
+            step.detail.push_str(&format!(" fins {{{}}}", fins.join(", ")));
+        }
+
+        step.eliminations[digit] = fish.eliminate;
+        step
+    }
+
+    /// Row and column fish that only hold once some fin cells are ruled out. Called sashimi when
+    /// a base line would be down to a single candidate without its fins. Fish whose technique
+    /// isn't `wanted` are passed over.
+    pub(super) fn finned_fish(&self, size: usize, wanted: impl Fn(Technique) -> bool) -> Option<Step> {
+        let rows = self.set_indexes_of(&(0..9).map(row_set).collect::<Vec<u128>>());
+        let columns = self.set_indexes_of(&(0..9).map(column_set).collect::<Vec<u128>>());
+
+        for (base_lines, cover_lines) in [(&rows, &columns), (&columns, &rows)] {
+            for digit in 0..9 {
+                let kind = |base: &[usize], _: &[usize], fins: u128| {
+                    if fins == 0 {
+                        return None;
+                    }
+
+                    let candidates = self.candidates[digit] & !self.cell_complete & !fins;
+                    let sashimi = base.iter().any(|i| (self.rules.sets[*i] & candidates).count_ones() <= 1);
+
+                    let technique = if sashimi {Technique::SashimiFish(size)} else {Technique::FinnedFish(size)};
+                    Some(technique).filter(|t| wanted(*t))
+                };
+
+                if let Some((technique, fish)) = self.fish_search(digit, size, base_lines, cover_lines, true, kind) {
+                    return Some(self.fish_step(technique, digit, fish));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Fish whose base and cover sets can be any of the sets in the rules. Franken fish mix boxes
+    /// into rows and columns, anything else (rows and columns on the same side, or other sets
+    /// like rubik's faces) is a mutant. Plain row and column fish are left to the other fish
+    /// finders, and fish whose technique isn't `wanted` are passed over.
+    pub(super) fn generalized_fish(&self, size: usize, wanted: impl Fn(Technique) -> bool) -> Option<Step> {
+        let all_sets: Vec<usize> = (0..self.rules.sets.len()).collect();
+
+        let rows = self.set_indexes_of(&(0..9).map(row_set).collect::<Vec<u128>>());
+        let columns = self.set_indexes_of(&(0..9).map(column_set).collect::<Vec<u128>>());
+        let boxes = self.set_indexes_of(&(0..9).map(box_set).collect::<Vec<u128>>());
+
+        let only_from = |sets: &[usize], a: &[usize], b: &[usize]| sets.iter().all(|i| a.contains(i) || b.contains(i));
+
+        let kind = |base: &[usize], cover: &[usize], _: u128| {
+            let basic = (only_from(base, &rows, &[]) && only_from(cover, &columns, &[]))
+                || (only_from(base, &columns, &[]) && only_from(cover, &rows, &[]));
+
+            if basic {
+                return None;
+            }
+
+            let franken = (only_from(base, &rows, &boxes) && only_from(cover, &columns, &boxes))
+                || (only_from(base, &columns, &boxes) && only_from(cover, &rows, &boxes));
+
+            let technique = if franken {Technique::FrankenFish(size)} else {Technique::MutantFish(size)};
+            Some(technique).filter(|t| wanted(*t))
+        };
+
+        for digit in 0..9 {
+            if let Some((technique, fish)) = self.fish_search(digit, size, &all_sets, &all_sets, true, kind) {
+                return Some(self.fish_step(technique, digit, fish));
+            }
+        }
+
+        None
+    }
+}
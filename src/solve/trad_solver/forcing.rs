@@ -0,0 +1,126 @@
+use crate::rules::describe_cell;
+use super::{BitmaskBoard, get_set_indexes};
+use super::steps::{describe_digits, Step, Technique};
+
+impl BitmaskBoard {
+
+    /// Fills in singles one at a time, at most `depth` of them. Returns `None` if the board
+    /// breaks on the way.
+    fn propagate_singles(&self, depth: usize) -> Option<BitmaskBoard> {
+        let mut brd = self.clone();
+        brd.update_candidates();
+
+        for _ in 0..depth {
+            if !brd.is_legal() {
+                return None;
+            }
+
+            match brd.singles() {
+                Some(step) => {brd.apply_step(&step)},
+                None => {break},
+            }
+        }
+
+        if brd.is_legal() {Some(brd)} else {None}
+    }
+
+    /// Places each digit in its cell in turn and follows the singles. A branch that breaks means
+    /// its placement is impossible; otherwise whatever every branch agrees on is true.
+    fn forcing_branches(&self, branches: &[(usize, u8)], depth: usize) -> Option<([u128; 9], [u128; 9], bool)> {
+        let mut results = vec![];
+
+        for (digit, index) in branches.iter().copied() {
+            let mut brd = self.clone();
+            brd.set_cell(digit, index);
+            brd.cell_complete |= 1 << index;
+
+            match brd.propagate_singles(depth) {
+                Some(result) => {results.push(result)},
+                None => {
+                    let mut eliminations = [0; 9];
+                    eliminations[digit] = 1 << index;
+                    return Some(([0; 9], eliminations, true));
+                },
+            }
+        }
+
+        let mut placements = [0; 9];
+        let mut eliminations = [0; 9];
+
+        for digit in 0..9 {
+            let unsolved = self.candidates[digit] & !self.cell_complete;
+
+            placements[digit] = results.iter().fold(unsolved, |acc, brd| acc & brd.candidates[digit] & brd.cell_complete);
+            eliminations[digit] = results.iter().fold(unsolved, |acc, brd| acc & !brd.candidates[digit]);
+        }
+
+        if placements.iter().chain(eliminations.iter()).all(|m| *m == 0) {
+            return None;
+        }
+
+        Some((placements, eliminations, false))
+    }
+
+    /// Forcing chains that only follow singles, up to `depth` of them per branch. Branches are
+    /// either the candidates of one cell or the places a digit can go in one set. A branch that
+    /// leads to a contradiction is a Nishio elimination, otherwise anything true in every branch
+    /// is true anyway. Steps whose technique isn't `wanted` are passed over.
+    pub(super) fn forcing_chain(&self, depth: usize, wanted: impl Fn(Technique) -> bool) -> Option<Step> {
+        let unsolved = !self.cell_complete & 0x1FFFFFFFFFFFFFFFFFFFF;
+
+        // fewest candidates first, those branch the least
+        let mut cells = if wanted(Technique::Nishio) || wanted(Technique::CellForcingChain) {get_set_indexes(unsolved)} else {vec![]};
+        cells.sort_by_key(|i| self.get_candidates(*i).count_ones());
+
+        for index in cells {
+            let branches: Vec<(usize, u8)> = self.candidates_vec(index).into_iter().map(|d| (d, index)).collect();
+
+            if let Some((placements, eliminations, contradiction)) = self.forcing_branches(&branches, depth) {
+                let technique = if contradiction {Technique::Nishio} else {Technique::CellForcingChain};
+
+                if !wanted(technique) {
+                    continue;
+                }
+
+                let mut step = Step::new(technique);
+                step.detail = format!("{} {}", describe_cell(index), describe_digits(self.get_candidates(index)));
+                step.placements = placements;
+                step.eliminations = eliminations;
+                return Some(step);
+            }
+        }
+
+        if !wanted(Technique::Nishio) && !wanted(Technique::UnitForcingChain) {
+            return None;
+        }
+
+        for (set_index, set) in self.rules.sets.iter().enumerate() {
+            for digit in 0..9 {
+                let places = self.candidates[digit] & set & unsolved;
+
+                if places.count_ones() < 2 {
+                    continue;
+                }
+
+                let branches: Vec<(usize, u8)> = get_set_indexes(places).into_iter().map(|i| (digit, i)).collect();
+
+                if let Some((placements, eliminations, contradiction)) = self.forcing_branches(&branches, depth) {
+                    let technique = if contradiction {Technique::Nishio} else {Technique::UnitForcingChain};
+
+                    if !wanted(technique) {
+                        continue;
+                    }
+
+                    let mut step = Step::new(technique);
+                    step.detail = (digit + 1).to_string();
+                    step.sets.push(self.rules.describe_set(set_index));
+                    step.placements = placements;
+                    step.eliminations = eliminations;
+                    return Some(step);
+                }
+            }
+        }
+
+        None
+    }
+}
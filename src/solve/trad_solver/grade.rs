@@ -0,0 +1,162 @@
+use std::fmt;
+
+use super::BitmaskBoard;
+use super::steps::Technique;
+
+/// Rough difficulty bands, decided by the hardest technique a puzzle needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    Easy,
+    Medium,
+    Hard,
+    Unfair,
+    Extreme,
+}
+
+impl Category {
+    fn from_rating(rating: f32) -> Category {
+        match rating {
+            r if r < 2.5 => Category::Easy,
+            r if r < 4.0 => Category::Medium,
+            r if r < 5.0 => Category::Hard,
+            r if r < 6.5 => Category::Unfair,
+            _ => Category::Extreme,
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Category::Easy => "Easy",
+            Category::Medium => "Medium",
+            Category::Hard => "Hard",
+            Category::Unfair => "Unfair",
+            Category::Extreme => "Extreme",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// How hard a puzzle is for the logic engine.
+#[derive(Debug, Clone)]
+pub struct Grade {
+    /// The hardest technique the solve needed, `None` if it took no steps at all.
+    pub hardest: Option<Technique>,
+    /// Weight of the hardest technique, by default its rating on roughly the Sudoku Explainer scale.
+    pub rating: f32,
+    /// Sum of the weights of every step taken.
+    pub score: f32,
+    pub steps: usize,
+    /// Whether logic alone solved it. If not, the puzzle counts as extreme whatever its rating.
+    pub solved: bool,
+    pub category: Category,
+}
+
+impl fmt::Display for Grade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:.1}", self.category, self.rating)?;
+
+        if let Some(hardest) = self.hardest {
+            write!(f, " (hardest {}", hardest)?;
+        } else {
+            write!(f, " (no steps")?;
+        }
+
+        write!(f, ", score {:.1} over {} step{})", self.score, self.steps, if self.steps == 1 {""} else {"s"})?;
+
+        if !self.solved {
+            write!(f, ", needs guessing")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Technique {
+    /// Difficulty of a technique, following the Sudoku Explainer ratings where it has them.
+    pub fn rating(&self) -> f32 {
+        match self {
+            Technique::FullHouse => 1.0,
+            Technique::HiddenSingle => 1.5,
+            Technique::CageSum => 2.0,
+            Technique::NakedSingle => 2.3,
+            Technique::LockedCandidates => 2.6,
+            Technique::NakedPair => 3.0,
+            Technique::XWing => 3.2,
+            Technique::HiddenPair => 3.4,
+            Technique::NakedTriple => 3.6,
+            Technique::Swordfish => 3.8,
+            Technique::HiddenTriple => 4.0,
+            Technique::Skyscraper => 4.0,
+            Technique::TwoStringKite => 4.1,
+            Technique::EmptyRectangle => 4.2,
+            Technique::TurbotFish => 4.2,
+            Technique::XYWing => 4.2,
+            Technique::XYZWing => 4.4,
+            Technique::WWing => 4.4,
+            Technique::UniqueRectangle(1) => 4.5,
+            Technique::UniqueRectangle(_) => 4.6,
+            Technique::HiddenRectangle => 4.7,
+            Technique::SimpleColorsTrap | Technique::SimpleColorsWrap => 4.5,
+            Technique::WXYZWing => 4.6,
+            Technique::NakedQuad => 5.0,
+            Technique::MultiColors(_) => 5.0,
+            Technique::SueDeCoq => 5.0,
+            Technique::Jellyfish => 5.2,
+            Technique::HiddenQuad => 5.4,
+            Technique::FinnedFish(size) => basic_fish_rating(*size) + 0.2,
+            Technique::SashimiFish(size) => basic_fish_rating(*size) + 0.3,
+            Technique::FrankenFish(size) => basic_fish_rating(*size) + 1.0,
+            Technique::MutantFish(size) => basic_fish_rating(*size) + 1.5,
+            Technique::ALSXZ => 5.5,
+            Technique::BUGPlusOne => 5.6,
+            Technique::ALSXYWing => 6.0,
+            Technique::XChain => 6.6,
+            Technique::XYChain => 6.6,
+            Technique::AIC => 7.0,
+            Technique::GroupedAIC => 7.3,
+            Technique::DeathBlossom => 7.5,
+            Technique::Nishio => 7.6,
+            Technique::CellForcingChain => 8.3,
+            Technique::UnitForcingChain => 8.5,
+        }
+    }
+}
+
+fn basic_fish_rating(size: usize) -> f32 {
+    match size {
+        2 => 3.2,
+        3 => 3.8,
+        _ => 5.2,
+    }
+}
+
+impl BitmaskBoard {
+    /// Solves the puzzle with logic alone and rates it by the hardest technique used, along with
+    /// a score that adds up the weight of every step. Weights come from the profile.
+    pub fn grade(&self) -> Grade {
+        let mut brd = self.clone();
+        let steps = brd.solve_logically();
+
+        let weight = |technique: Technique| self.options.profile.weight(technique);
+
+        let hardest = steps.iter()
+            .map(|step| step.technique)
+            .max_by(|a, b| weight(*a).total_cmp(&weight(*b)));
+
+        let rating = hardest.map_or(0.0, weight);
+        let score = steps.iter().fold(0.0, |acc, step| acc + weight(step.technique));
+        let solved = brd.is_solved();
+
+        Grade {
+            hardest,
+            rating,
+            score,
+            steps: steps.len(),
+            solved,
+            category: if solved {Category::from_rating(rating)} else {Category::Extreme},
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use super::BitmaskBoard;
+use super::steps::{Step, Technique};
+
+impl BitmaskBoard {
+
+    /// If every candidate for a digit in set A also lies in set B, the digit has to go somewhere
+    /// in the overlap, so it can be removed from the rest of B. With boxes and lines this covers
+    /// both pointing and claiming, and it works the same for any other sets that overlap.
+    pub(super) fn locked_candidates(&self) -> Option<Step> {
+        for (a_index, a) in self.rules.sets.iter().enumerate() {
+            for (b_index, b) in self.rules.sets.iter().enumerate() {
+                if a_index == b_index || a & b == 0 || a == b {
+                    continue;
+                }
+
+                for digit in 0..9 {
+                    let in_a = self.candidates[digit] & a & !self.cell_complete;
+
+                    if in_a == 0 || in_a & !b != 0 {
+                        continue;
+                    }
+
+                    let eliminate = self.candidates[digit] & b & !a & !self.cell_complete;
+
+                    if eliminate != 0 {
+                        let mut step = Step::new(Technique::LockedCandidates);
+                        step.detail = (digit + 1).to_string();
+                        step.sets.push(self.rules.describe_set(a_index));
+                        step.sets.push(self.rules.describe_set(b_index));
+                        step.eliminations[digit] = eliminate;
+                        return Some(step);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,55 @@
+use super::{BitmaskBoard, get_set_indexes};
+use super::steps::{describe_digits, Step, Technique};
+
+impl BitmaskBoard {
+
+    /// A cell of a cage can only be a digit from a combination that still fits. A digit in
+    /// every combination has to be in the cage, so it goes in its only cell there or can't be
+    /// anywhere that sees all the cage cells it could be in.
+    pub(super) fn cage_sum(&self) -> Option<Step> {
+        for cage in &self.rules.cages {
+            let combinations = cage.combinations_left(&self.candidates);
+
+            // no combination at all is a broken board, is_legal catches that
+            if combinations.is_empty() {
+                continue;
+            }
+
+            let mut step = Step::new(Technique::CageSum);
+
+            for index in get_set_indexes(cage.cells & !self.cell_complete) {
+                let candidates = self.get_candidates(index);
+                let allowed = combinations.iter().fold(0, |acc, combination| acc | (candidates & combination));
+
+                for digit in (0..9).filter(|d| candidates & !allowed & (1 << d) != 0) {
+                    step.eliminations[digit] |= 1 << index;
+                }
+            }
+
+            let required = combinations.iter().fold(0x1FF, |acc, combination| acc & combination);
+
+            for digit in (0..9).filter(|d| required & (1 << d) != 0) {
+                let places = self.candidates[digit] & cage.cells;
+
+                if places & self.cell_complete != 0 {
+                    continue;
+                }
+
+                if places.count_ones() == 1 {
+                    step.placements[digit] = places;
+                }
+
+                step.eliminations[digit] |= self.candidates[digit] & !self.cell_complete & self.seen_by_all(places) & !cage.cells;
+            }
+
+            if !step.is_empty() {
+                let combinations: Vec<String> = combinations.into_iter().map(describe_digits).collect();
+                step.detail = combinations.join(" ");
+                step.sets.push(cage.to_string());
+                return Some(step);
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,242 @@
+use anyhow::{bail, Result};
+
+use super::BitmaskBoard;
+use super::steps::{Step, Technique};
+
+/// Which techniques the logic engine uses, in the order it tries them, and how much each one
+/// counts towards a grade.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub techniques: Vec<(Technique, f32)>,
+}
+
+/// The techniques the engine uses by default, in the order it tries them. They go from the
+/// lowest rating up, so a grade reflects the easiest technique that gets each step done.
+const DEFAULT_ORDER: [Technique; 55] = [
+    Technique::FullHouse,
+    Technique::HiddenSingle,
+    Technique::CageSum,
+    Technique::NakedSingle,
+    Technique::LockedCandidates,
+    Technique::NakedPair,
+    Technique::XWing,
+    Technique::HiddenPair,
+    Technique::FinnedFish(2),
+    Technique::SashimiFish(2),
+    Technique::NakedTriple,
+    Technique::Swordfish,
+    Technique::HiddenTriple,
+    Technique::Skyscraper,
+    Technique::FinnedFish(3),
+    Technique::TwoStringKite,
+    Technique::SashimiFish(3),
+    Technique::EmptyRectangle,
+    Technique::TurbotFish,
+    Technique::XYWing,
+    Technique::FrankenFish(2),
+    Technique::XYZWing,
+    Technique::WWing,
+    Technique::UniqueRectangle(1),
+    Technique::SimpleColorsTrap,
+    Technique::SimpleColorsWrap,
+    Technique::UniqueRectangle(2),
+    Technique::UniqueRectangle(3),
+    Technique::UniqueRectangle(4),
+    Technique::UniqueRectangle(5),
+    Technique::UniqueRectangle(6),
+    Technique::WXYZWing,
+    Technique::HiddenRectangle,
+    Technique::MutantFish(2),
+    Technique::FrankenFish(3),
+    Technique::NakedQuad,
+    Technique::MultiColors(1),
+    Technique::MultiColors(2),
+    Technique::SueDeCoq,
+    Technique::Jellyfish,
+    Technique::MutantFish(3),
+    Technique::FinnedFish(4),
+    Technique::HiddenQuad,
+    Technique::SashimiFish(4),
+    Technique::ALSXZ,
+    Technique::BUGPlusOne,
+    Technique::ALSXYWing,
+    Technique::XChain,
+    Technique::XYChain,
+    Technique::AIC,
+    Technique::GroupedAIC,
+    Technique::DeathBlossom,
+    Technique::Nishio,
+    Technique::CellForcingChain,
+    Technique::UnitForcingChain,
+];
+
+/// Techniques left out of the default profile because they take too long, but that a profile
+/// can still ask for.
+const OPTIONAL: [Technique; 2] = [
+    Technique::FrankenFish(4),
+    Technique::MutantFish(4),
+];
+
+impl Default for Profile {
+    fn default() -> Profile {
+        Profile::from_techniques(DEFAULT_ORDER)
+    }
+}
+
+impl Profile {
+    /// The given techniques in order, each weighted by its usual rating.
+    pub fn from_techniques(techniques: impl IntoIterator<Item = Technique>) -> Profile {
+        Profile {techniques: techniques.into_iter().map(|t| (t, t.rating())).collect()}
+    }
+
+    /// Full houses, hidden singles and naked singles, which is all the guessing search uses.
+    pub fn singles() -> Profile {
+        Profile::from_techniques([Technique::FullHouse, Technique::HiddenSingle, Technique::NakedSingle])
+    }
+
+    /// One of the built in profiles: everything, only singles, or everything but chains.
+    pub fn preset(name: &str) -> Option<Profile> {
+        let techniques: Vec<Technique> = match name {
+            "default" => {return Some(Profile::default())},
+            "singles" => {return Some(Profile::singles())},
+            "no-chains" => DEFAULT_ORDER.iter().copied().filter(|t| !t.is_chain()).collect(),
+            _ => {return None},
+        };
+
+        Some(Profile::from_techniques(techniques))
+    }
+
+    /// Reads a profile from text with one technique per line (or comma separated), named the way
+    /// steps print them, each optionally followed by `= weight`. Anything after a `#` is ignored.
+    ///
+    /// ```text
+    /// # singles and pairs only, pairs counting double
+    /// Hidden Single
+    /// Naked Single
+    /// Naked Pair = 6.0
+    /// Hidden Pair = 6.8
+    /// ```
+    pub fn parse(text: &str) -> Result<Profile> {
+        let mut techniques = vec![];
+
+        let entries = text.lines()
+            .map(|line| line.split('#').next().unwrap_or(""))
+            .flat_map(|line| line.split(','))
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty());
+
+        for entry in entries {
+            let (name, weight) = match entry.split_once('=') {
+                Some((name, weight)) => (name.trim(), Some(weight.trim())),
+                None => (entry, None),
+            };
+
+            let technique = match DEFAULT_ORDER.iter().chain(&OPTIONAL).find(|t| t.to_string().eq_ignore_ascii_case(name)) {
+                Some(technique) => *technique,
+                None => bail!("unknown technique \"{}\"", name),
+            };
+
+            let weight = match weight {
+                Some(weight) => match weight.parse::<f32>() {
+                    Ok(weight) => weight,
+                    Err(_) => bail!("bad weight \"{}\" for {}", weight, technique),
+                },
+                None => technique.rating(),
+            };
+
+            if techniques.iter().any(|(t, _)| *t == technique) {
+                bail!("{} is listed twice", technique);
+            }
+
+            techniques.push((technique, weight));
+        }
+
+        if techniques.is_empty() {
+            bail!("the profile has no techniques");
+        }
+
+        Ok(Profile {techniques})
+    }
+
+    /// Whether the profile uses this technique at all.
+    pub fn contains(&self, technique: Technique) -> bool {
+        self.techniques.iter().any(|(t, _)| *t == technique)
+    }
+
+    /// How much a step of this technique counts, its usual rating if the profile doesn't have it.
+    pub fn weight(&self, technique: Technique) -> f32 {
+        self.techniques.iter()
+            .find(|(t, _)| *t == technique)
+            .map_or(technique.rating(), |(_, weight)| *weight)
+    }
+}
+
+impl Technique {
+    /// Chains of any kind, including forcing chains.
+    pub fn is_chain(&self) -> bool {
+        matches!(self, Technique::XChain | Technique::XYChain | Technique::AIC | Technique::GroupedAIC
+            | Technique::Nishio | Technique::CellForcingChain | Technique::UnitForcingChain)
+    }
+
+    /// Techniques found by the same search share a finder, named after one of them, so the
+    /// search only has to run once.
+    pub(super) fn finder(&self) -> Technique {
+        match self {
+            Technique::SashimiFish(size) => Technique::FinnedFish(*size),
+            Technique::MutantFish(size) => Technique::FrankenFish(*size),
+            Technique::CellForcingChain | Technique::UnitForcingChain => Technique::Nishio,
+            _ => *self,
+        }
+    }
+}
+
+impl BitmaskBoard {
+
+    /// Looks for a step of this technique, or of any technique in the profile that shares its
+    /// finder. Uniqueness techniques also need `assume_unique` and forcing chains a `forcing_depth`.
+    pub(super) fn find_step(&self, technique: Technique) -> Option<Step> {
+        if technique.relies_on_uniqueness() && !self.options.assume_unique {
+            return None;
+        }
+
+        let wanted = |t: Technique| self.options.profile.contains(t);
+
+        match technique {
+            Technique::FullHouse => self.full_house(),
+            Technique::HiddenSingle => self.hidden_single(),
+            Technique::NakedSingle => self.naked_single(),
+            Technique::NakedPair => self.naked_subset(2),
+            Technique::HiddenPair => self.hidden_subset(2),
+            Technique::NakedTriple => self.naked_subset(3),
+            Technique::HiddenTriple => self.hidden_subset(3),
+            Technique::NakedQuad => self.naked_subset(4),
+            Technique::HiddenQuad => self.hidden_subset(4),
+            Technique::LockedCandidates => self.locked_candidates(),
+            Technique::XWing => self.basic_fish(2),
+            Technique::Swordfish => self.basic_fish(3),
+            Technique::Jellyfish => self.basic_fish(4),
+            Technique::FinnedFish(size) | Technique::SashimiFish(size) => self.finned_fish(size, wanted),
+            Technique::FrankenFish(size) | Technique::MutantFish(size) => self.generalized_fish(size, wanted),
+            Technique::XYWing => self.xyz_wing(2),
+            Technique::XYZWing => self.xyz_wing(3),
+            Technique::WXYZWing => self.xyz_wing(4),
+            Technique::WWing => self.w_wing(),
+            Technique::Skyscraper | Technique::TwoStringKite | Technique::TurbotFish => self.turbot_fish(technique),
+            Technique::EmptyRectangle => self.empty_rectangle(),
+            Technique::SimpleColorsTrap | Technique::SimpleColorsWrap => self.simple_coloring(technique),
+            Technique::MultiColors(kind) => self.multi_coloring(kind),
+            Technique::XChain | Technique::XYChain | Technique::AIC | Technique::GroupedAIC => self.chain(technique),
+            Technique::Nishio | Technique::CellForcingChain | Technique::UnitForcingChain => {
+                self.options.forcing_depth.and_then(|depth| self.forcing_chain(depth, wanted))
+            },
+            Technique::UniqueRectangle(kind) => self.unique_rectangle(kind),
+            Technique::HiddenRectangle => self.hidden_rectangle(),
+            Technique::BUGPlusOne => self.bug_plus_one(),
+            Technique::ALSXZ => self.als_xz(),
+            Technique::ALSXYWing => self.als_xy_wing(),
+            Technique::DeathBlossom => self.death_blossom(),
+            Technique::SueDeCoq => self.sue_de_coq(),
+            Technique::CageSum => self.cage_sum(),
+        }
+    }
+}
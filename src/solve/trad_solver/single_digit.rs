@@ -0,0 +1,170 @@
+use crate::rules::{box_set, column_set, describe_cell, row_set};
+use super::{BitmaskBoard, combinations};
+use super::steps::{Step, Technique};
+
+/// Two cells that are the only places left for a digit in one of the sets, so one of them has
+/// to hold it.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct StrongLink {
+    pub set: usize,
+    pub ends: (u8, u8),
+}
+
+impl BitmaskBoard {
+
+    /// Every strong link on `digit`, one per set with exactly two candidates left. Sets that
+    /// happen to link the same two cells only show up once.
+    pub(super) fn strong_links(&self, digit: usize) -> Vec<StrongLink> {
+        let candidates = self.candidates[digit] & !self.cell_complete;
+        let mut links: Vec<StrongLink> = vec![];
+
+        for (set_index, set) in self.rules.sets.iter().enumerate() {
+            let in_set = candidates & set;
+
+            if in_set.count_ones() != 2 {
+                continue;
+            }
+
+            let first = in_set.trailing_zeros() as u8;
+            let second = (127 - in_set.leading_zeros()) as u8;
+
+            if !links.iter().any(|link| link.ends == (first, second)) {
+                links.push(StrongLink {set: set_index, ends: (first, second)});
+            }
+        }
+
+        links
+    }
+
+    fn is_line(&self, set_index: usize, lines: fn(usize) -> u128) -> bool {
+        (0..9).any(|i| self.rules.sets[set_index] == lines(i))
+    }
+
+    /// Two strong links on a digit with one end of each seeing the other. Those two ends can't
+    /// both be the digit, so one of the far ends is, and anything seeing both far ends isn't.
+    /// `technique` picks the shape: Skyscraper for two parallel lines, Two-String Kite for a row
+    /// and a column meeting in a box, Turbot Fish for anything else.
+    pub(super) fn turbot_fish(&self, technique: Technique) -> Option<Step> {
+        for digit in 0..9 {
+            let candidates = self.candidates[digit] & !self.cell_complete;
+            let links = self.strong_links(digit);
+
+            for pair in combinations(&(0..links.len()).collect::<Vec<usize>>(), 2) {
+                let (first, second) = (links[pair[0]], links[pair[1]]);
+                let (a, b) = (first.ends, second.ends);
+
+                if a.0 == b.0 || a.0 == b.1 || a.1 == b.0 || a.1 == b.1 {
+                    continue;
+                }
+
+                let rows = self.is_line(first.set, row_set) && self.is_line(second.set, row_set);
+                let columns = self.is_line(first.set, column_set) && self.is_line(second.set, column_set);
+                let crossing = (self.is_line(first.set, row_set) && self.is_line(second.set, column_set))
+                    || (self.is_line(first.set, column_set) && self.is_line(second.set, row_set));
+
+                for (near_1, far_1) in [(a.0, a.1), (a.1, a.0)] {
+                    for (near_2, far_2) in [(b.0, b.1), (b.1, b.0)] {
+                        if !self.bitmask_get(self.peers(near_1), near_2) {
+                            continue;
+                        }
+
+                        let shape = if rows || columns {
+                            Technique::Skyscraper
+                        } else if crossing && (0..9).any(|i| box_set(i) & (1 << near_1) != 0 && box_set(i) & (1 << near_2) != 0) {
+                            Technique::TwoStringKite
+                        } else {
+                            Technique::TurbotFish
+                        };
+
+                        if shape != technique {
+                            continue;
+                        }
+
+                        let eliminate = candidates & self.peers(far_1) & self.peers(far_2);
+
+                        if eliminate != 0 {
+                            let mut step = Step::new(technique);
+                            step.detail = format!("{} {}={}-{}={}", digit + 1, describe_cell(far_1), describe_cell(near_1), describe_cell(near_2), describe_cell(far_2));
+                            step.sets.push(self.rules.describe_set(first.set));
+                            step.sets.push(self.rules.describe_set(second.set));
+                            step.eliminations[digit] = eliminate;
+                            return Some(step);
+                        }
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// A box whose candidates for a digit all lie in one row and one column, together with a
+    /// strong link in a line crossing that row (or column) outside the box. Either the near end
+    /// of the link is the digit, pushing the box's digit into its column, or the far end is, so
+    /// the cell where the far end's line meets the box's column can't be the digit.
+    pub(super) fn empty_rectangle(&self) -> Option<Step> {
+        // leans on rows, columns and boxes all being sets
+        if !self.rules.has_boxes() {
+            return None;
+        }
+
+        let position = |set: u128| self.rules.sets.iter().position(|s| *s == set).unwrap();
+
+        for sudoku_box in 0..9 {
+            let box_index = position(box_set(sudoku_box));
+
+            let box_rows: Vec<usize> = (0..3).map(|i| (sudoku_box / 3) * 3 + i).collect();
+            let box_columns: Vec<usize> = (0..3).map(|i| (sudoku_box % 3) * 3 + i).collect();
+
+            for digit in 0..9 {
+                let candidates = self.candidates[digit] & !self.cell_complete;
+                let in_box = candidates & box_set(sudoku_box);
+
+                for row in box_rows.iter().copied() {
+                    for column in box_columns.iter().copied() {
+                        let (row_mask, column_mask) = (row_set(row), column_set(column));
+
+                        // needs candidates on both arms, otherwise it's just locked candidates
+                        if in_box & !(row_mask | column_mask) != 0 || in_box & row_mask & !column_mask == 0 || in_box & column_mask & !row_mask == 0 {
+                            continue;
+                        }
+
+                        // links in columns crossing the row, then links in rows crossing the column
+                        let crossing_links = (0..9).filter(|c| !box_columns.contains(c)).map(|c| (column_set(c), row * 9 + c, true))
+                            .chain((0..9).filter(|r| !box_rows.contains(r)).map(|r| (row_set(r), r * 9 + column, false)));
+
+                        for (line, near, is_column) in crossing_links {
+                            let link = candidates & line;
+
+                            if link.count_ones() != 2 || link & (1 << near) == 0 {
+                                continue;
+                            }
+
+                            let far = (link & !(1 << near)).trailing_zeros() as usize;
+                            let (far_row, far_column) = (far / 9, far % 9);
+
+                            let target = if is_column {
+                                if box_rows.contains(&far_row) {continue}
+                                far_row * 9 + column
+                            } else {
+                                if box_columns.contains(&far_column) {continue}
+                                row * 9 + far_column
+                            };
+
+                            if candidates & (1 << target) != 0 {
+                                let mut step = Step::new(Technique::EmptyRectangle);
+                                step.detail = format!("{} {}={}", digit + 1, describe_cell(near as u8), describe_cell(far as u8));
+                                step.sets.push(self.rules.describe_set(box_index));
+                                step.sets.push(self.rules.describe_set(position(line)));
+                                step.eliminations[digit] = 1 << target;
+                                return Some(step);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
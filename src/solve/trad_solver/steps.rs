@@ -0,0 +1,201 @@
+use std::fmt;
+
+use crate::rules::describe_cell;
+use super::get_set_indexes;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Technique {
+    FullHouse,
+    HiddenSingle,
+    NakedSingle,
+    NakedPair,
+    HiddenPair,
+    NakedTriple,
+    HiddenTriple,
+    NakedQuad,
+    HiddenQuad,
+    LockedCandidates,
+    XWing,
+    Swordfish,
+    Jellyfish,
+    FinnedFish(usize),
+    SashimiFish(usize),
+    FrankenFish(usize),
+    MutantFish(usize),
+    XYWing,
+    XYZWing,
+    WWing,
+    WXYZWing,
+    Skyscraper,
+    TwoStringKite,
+    EmptyRectangle,
+    TurbotFish,
+    SimpleColorsTrap,
+    SimpleColorsWrap,
+    MultiColors(usize),
+    XChain,
+    XYChain,
+    AIC,
+    GroupedAIC,
+    Nishio,
+    CellForcingChain,
+    UnitForcingChain,
+    UniqueRectangle(usize),
+    HiddenRectangle,
+    BUGPlusOne,
+    ALSXZ,
+    ALSXYWing,
+    DeathBlossom,
+    SueDeCoq,
+    CageSum,
+}
+
+impl Technique {
+    /// Whether the deduction only holds if the puzzle has a single solution.
+    pub fn relies_on_uniqueness(&self) -> bool {
+        matches!(self, Technique::UniqueRectangle(_) | Technique::HiddenRectangle | Technique::BUGPlusOne)
+    }
+}
+
+fn fish_name(size: usize) -> &'static str {
+    match size {
+        2 => "X-Wing",
+        3 => "Swordfish",
+        4 => "Jellyfish",
+        _ => "Fish",
+    }
+}
+
+impl fmt::Display for Technique {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Technique::FullHouse => "Full House",
+            Technique::HiddenSingle => "Hidden Single",
+            Technique::NakedSingle => "Naked Single",
+            Technique::NakedPair => "Naked Pair",
+            Technique::HiddenPair => "Hidden Pair",
+            Technique::NakedTriple => "Naked Triple",
+            Technique::HiddenTriple => "Hidden Triple",
+            Technique::NakedQuad => "Naked Quad",
+            Technique::HiddenQuad => "Hidden Quad",
+            Technique::LockedCandidates => "Locked Candidates",
+            Technique::XWing => "X-Wing",
+            Technique::Swordfish => "Swordfish",
+            Technique::Jellyfish => "Jellyfish",
+            Technique::FinnedFish(size) => {return write!(f, "Finned {}", fish_name(*size))},
+            Technique::SashimiFish(size) => {return write!(f, "Sashimi {}", fish_name(*size))},
+            Technique::FrankenFish(size) => {return write!(f, "Franken {}", fish_name(*size))},
+            Technique::MutantFish(size) => {return write!(f, "Mutant {}", fish_name(*size))},
+            Technique::XYWing => "XY-Wing",
+            Technique::XYZWing => "XYZ-Wing",
+            Technique::WWing => "W-Wing",
+            Technique::WXYZWing => "WXYZ-Wing",
+            Technique::Skyscraper => "Skyscraper",
+            Technique::TwoStringKite => "Two-String Kite",
+            Technique::EmptyRectangle => "Empty Rectangle",
+            Technique::TurbotFish => "Turbot Fish",
+            Technique::SimpleColorsTrap => "Simple Colors Trap",
+            Technique::SimpleColorsWrap => "Simple Colors Wrap",
+            Technique::MultiColors(kind) => {return write!(f, "Multi Colors {}", kind)},
+            Technique::XChain => "X-Chain",
+            Technique::XYChain => "XY-Chain",
+            Technique::AIC => "AIC",
+            Technique::GroupedAIC => "Grouped AIC",
+            Technique::Nishio => "Nishio",
+            Technique::CellForcingChain => "Cell Forcing Chain",
+            Technique::UnitForcingChain => "Unit Forcing Chain",
+            Technique::UniqueRectangle(kind) => {return write!(f, "Unique Rectangle Type {}", kind)},
+            Technique::HiddenRectangle => "Hidden Rectangle",
+            Technique::BUGPlusOne => "BUG+1",
+            Technique::ALSXZ => "ALS-XZ",
+            Technique::ALSXYWing => "ALS-XY-Wing",
+            Technique::DeathBlossom => "Death Blossom",
+            Technique::SueDeCoq => "Sue de Coq",
+            Technique::CageSum => "Cage Sum",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Formats a 9 bit digit mask like `{3,7}`.
+pub(super) fn describe_digits(digits: u16) -> String {
+    let digits: Vec<String> = (0..9)
+        .filter(|d| digits & (1 << d) != 0)
+        .map(|d| (d + 1).to_string())
+        .collect();
+
+    format!("{{{}}}", digits.join(","))
+}
+
+/// One deduction made by the logic engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub technique: Technique,
+    /// Anything technique specific worth saying, e.g. the digits of a subset.
+    pub detail: String,
+    /// The sets that justify the deduction.
+    pub sets: Vec<String>,
+    /// Per digit bitmasks of the cells the digit gets placed in.
+    pub placements: [u128; 9],
+    /// Per digit bitmasks of the cells the digit gets removed from.
+    pub eliminations: [u128; 9],
+    /// The two colour classes behind a colouring step, for showing on the board.
+    pub colors: Option<[u128; 2]>,
+}
+
+impl Step {
+    pub fn new(technique: Technique) -> Step {
+        Step {
+            technique,
+            detail: String::new(),
+            sets: vec![],
+            placements: [0; 9],
+            eliminations: [0; 9],
+            colors: None,
+        }
+    }
+
+    /// Every cell this step places a digit in or removes a candidate from.
+    pub fn cells_affected(&self) -> u128 {
+        self.placements.iter().chain(self.eliminations.iter()).fold(0, |acc, x| acc | x)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells_affected() == 0
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.technique)?;
+
+        if self.technique.relies_on_uniqueness() {
+            write!(f, " (assuming a unique solution)")?;
+        }
+
+        if !self.detail.is_empty() {
+            write!(f, " {}", self.detail)?;
+        }
+
+        if !self.sets.is_empty() {
+            write!(f, " in {}", self.sets.join(", "))?;
+        }
+
+        let mut results = vec![];
+
+        for (digit, mask) in self.placements.iter().enumerate() {
+            for index in get_set_indexes(*mask) {
+                results.push(format!("{}={}", describe_cell(index), digit + 1));
+            }
+        }
+
+        for (digit, mask) in self.eliminations.iter().enumerate() {
+            for index in get_set_indexes(*mask) {
+                results.push(format!("{}<>{}", describe_cell(index), digit + 1));
+            }
+        }
+
+        write!(f, ": {}", results.join(", "))
+    }
+}
@@ -0,0 +1,99 @@
+use super::{BitmaskBoard, combinations, get_set_indexes};
+use super::steps::{describe_digits, Step, Technique};
+
+impl BitmaskBoard {
+
+    /// `size` unsolved cells in a set that only have `size` digits between them, so those digits
+    /// can go nowhere else in the set.
+    pub(super) fn naked_subset(&self, size: usize) -> Option<Step> {
+        let technique = match size {
+            2 => Technique::NakedPair,
+            3 => Technique::NakedTriple,
+            4 => Technique::NakedQuad,
+            _ => unreachable!("only pairs, triples and quads are subsets"),
+        };
+
+        for (set_index, set) in self.rules.sets.iter().enumerate() {
+            let unsolved = get_set_indexes(set & !self.cell_complete);
+
+            if unsolved.len() <= size {
+                continue;
+            }
+
+            for cells in combinations(&unsolved, size) {
+                let digits = cells.iter().fold(0, |acc, i| acc | self.get_candidates(*i));
+
+                if digits.count_ones() as usize != size {
+                    continue;
+                }
+
+                let subset_mask = cells.iter().fold(0u128, |acc, i| acc | (1 << i));
+                let mut step = Step::new(technique);
+
+                for digit in 0..9 {
+                    if digits & (1 << digit) != 0 {
+                        step.eliminations[digit] = self.candidates[digit] & set & !self.cell_complete & !subset_mask;
+                    }
+                }
+
+                if !step.is_empty() {
+                    step.detail = describe_digits(digits);
+                    step.sets.push(self.rules.describe_set(set_index));
+                    return Some(step);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// `size` digits that can only go in the same `size` cells of a set, so nothing else can go
+    /// in those cells.
+    pub(super) fn hidden_subset(&self, size: usize) -> Option<Step> {
+        let technique = match size {
+            2 => Technique::HiddenPair,
+            3 => Technique::HiddenTriple,
+            4 => Technique::HiddenQuad,
+            _ => unreachable!("only pairs, triples and quads are subsets"),
+        };
+
+        for (set_index, set) in self.rules.sets.iter().enumerate() {
+            let unsolved = set & !self.cell_complete;
+
+            let open_digits: Vec<usize> = (0..9)
+                .filter(|d| self.candidates[*d] & unsolved != 0)
+                .collect();
+
+            if open_digits.len() <= size {
+                continue;
+            }
+
+            for digits in combinations(&open_digits, size) {
+                let cells = digits.iter().fold(0, |acc, d| acc | (self.candidates[*d] & unsolved));
+
+                if cells.count_ones() as usize != size {
+                    continue;
+                }
+
+                let mut step = Step::new(technique);
+                let mut digit_mask = 0;
+
+                for digit in 0..9 {
+                    if digits.contains(&digit) {
+                        digit_mask |= 1 << digit;
+                    } else {
+                        step.eliminations[digit] = self.candidates[digit] & cells;
+                    }
+                }
+
+                if !step.is_empty() {
+                    step.detail = describe_digits(digit_mask);
+                    step.sets.push(self.rules.describe_set(set_index));
+                    return Some(step);
+                }
+            }
+        }
+
+        None
+    }
+}
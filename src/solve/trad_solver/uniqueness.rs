@@ -0,0 +1,266 @@
+use crate::rules::describe_cell;
+use super::{BitmaskBoard, combinations, get_set_indexes};
+use super::steps::{describe_digits, Step, Technique};
+
+/// Four unsolved cells in two rows and two columns that all still have the digits `a` and `b`.
+/// Corners are in reading order, so 0 and 3 are diagonal, as are 1 and 2.
+#[derive(Debug, Clone, Copy)]
+struct Rectangle {
+    corners: [u8; 4],
+    a: usize,
+    b: usize,
+}
+
+impl Rectangle {
+    fn mask(&self) -> u128 {
+        self.corners.iter().fold(0, |acc, i| acc | (1 << i))
+    }
+
+    fn digits(&self) -> u16 {
+        (1 << self.a) | (1 << self.b)
+    }
+
+    fn opposite(corner: usize) -> usize {
+        3 - corner
+    }
+}
+
+impl BitmaskBoard {
+
+    /// Every rectangle that would be a deadly pattern, i.e. where swapping `a` and `b` would give
+    /// a second solution. That needs every set to hold either none of the corners or exactly two
+    /// on the same row or column, which with normal rules means the rectangle spans two boxes.
+    /// A killer cage also has to keep its sum, so it needs two corners of a side or all four.
+    /// Chess moves from the corners to the rest of the grid can stop the swap, so there are none
+    /// with chess rules.
+    fn rectangles(&self) -> Vec<Rectangle> {
+        let mut rectangles = vec![];
+
+        if !self.rules.anti_moves().is_empty() {
+            return rectangles;
+        }
+
+        for rows in combinations(&(0..9u8).collect::<Vec<u8>>(), 2) {
+            for columns in combinations(&(0..9u8).collect::<Vec<u8>>(), 2) {
+                let corners = [rows[0] * 9 + columns[0], rows[0] * 9 + columns[1], rows[1] * 9 + columns[0], rows[1] * 9 + columns[1]];
+                let mask = corners.iter().fold(0u128, |acc, i| acc | (1 << i));
+
+                if mask & self.cell_complete != 0 {
+                    continue;
+                }
+
+                let pairs = [(1 << corners[0]) | (1 << corners[1]), (1 << corners[2]) | (1 << corners[3]),
+                             (1 << corners[0]) | (1 << corners[2]), (1 << corners[1]) | (1 << corners[3])];
+
+                let deadly = self.rules.sets.iter().all(|set| set & mask == 0 || pairs.contains(&(set & mask)))
+                    && self.rules.cages.iter().map(|cage| cage.cells & mask).all(|caged| caged == 0 || caged == mask || pairs.contains(&caged));
+
+                if !deadly {
+                    continue;
+                }
+
+                let common = corners.iter().fold(0x1FF, |acc, i| acc & self.get_candidates(*i));
+
+                for digits in combinations(&(0..9).filter(|d| common & (1 << d) != 0).collect::<Vec<usize>>(), 2) {
+                    rectangles.push(Rectangle {corners, a: digits[0], b: digits[1]});
+                }
+            }
+        }
+
+        rectangles
+    }
+
+    fn rectangle_step(&self, technique: Technique, rectangle: &Rectangle) -> Step {
+        let corners: Vec<String> = rectangle.corners.iter().map(|i| describe_cell(*i)).collect();
+
+        let mut step = Step::new(technique);
+        step.detail = format!("{} {}", describe_digits(rectangle.digits()), corners.join(", "));
+        step
+    }
+
+    /// Whether every side of the rectangle lies in some set, so neighbouring corners can't match.
+    fn sides_linked(&self, rectangle: &Rectangle) -> bool {
+        [(0, 1), (2, 3), (0, 2), (1, 3)].iter()
+            .all(|(p, q)| self.bitmask_get(self.peers(rectangle.corners[*p]), rectangle.corners[*q]))
+    }
+
+    /// Sets that contain every cell in `cells`.
+    fn shared_sets(&self, cells: u128) -> Vec<usize> {
+        (0..self.rules.sets.len()).filter(|i| cells & !self.rules.sets[*i] == 0).collect()
+    }
+
+    /// Unique rectangles of type 1 to 6. The corners with nothing but `a` and `b` are the floor,
+    /// the rest the roof, and the roof has to stop the pattern from becoming deadly.
+    pub(super) fn unique_rectangle(&self, kind: usize) -> Option<Step> {
+        for rectangle in self.rectangles() {
+            let (a, b) = (rectangle.a, rectangle.b);
+            let extras = |corner: usize| self.get_candidates(rectangle.corners[corner]) & !rectangle.digits();
+
+            let roof: Vec<usize> = (0..4).filter(|c| extras(*c) != 0).collect();
+            let roof_mask = roof.iter().fold(0u128, |acc, c| acc | (1 << rectangle.corners[*c]));
+            let roof_extras = roof.iter().fold(0, |acc, c| acc | extras(*c));
+            let in_line = roof.len() == 2 && roof[0] + roof[1] != 3;
+
+            let mut step = self.rectangle_step(Technique::UniqueRectangle(kind), &rectangle);
+
+            match kind {
+                // only one corner has anything else, so it can't be a or b
+                1 if roof.len() == 1 => {
+                    step.eliminations[a] = roof_mask;
+                    step.eliminations[b] = roof_mask;
+                },
+                // the roof shares a single extra digit, which has to be in one of the roof cells
+                2 | 5 if (2..=3).contains(&roof.len()) && roof_extras.count_ones() == 1 && roof.iter().all(|c| extras(*c) == roof_extras) => {
+                    if (kind == 2) != in_line {
+                        continue;
+                    }
+
+                    let digit = roof_extras.trailing_zeros() as usize;
+                    step.eliminations[digit] = self.candidates[digit] & !self.cell_complete & self.seen_by_all(roof_mask) & !rectangle.mask();
+                },
+                // the roof acts as one cell with its extra digits and makes a naked subset
+                3 if in_line => {
+                    let unsolved = !self.cell_complete & 0x1FFFFFFFFFFFFFFFFFFFF;
+
+                    for set_index in self.shared_sets(roof_mask) {
+                        let others = get_set_indexes(self.rules.sets[set_index] & unsolved & !rectangle.mask());
+
+                        for size in 1..=3 {
+                            for subset in combinations(&others, size) {
+                                let digits = subset.iter().fold(roof_extras, |acc, i| acc | self.get_candidates(*i));
+
+                                if digits.count_ones() as usize != size + 1 {
+                                    continue;
+                                }
+
+                                let subset_mask = subset.iter().fold(roof_mask, |acc, i| acc | (1 << i));
+                                let rest = self.rules.sets[set_index] & unsolved & !subset_mask & !rectangle.mask();
+
+                                for digit in (0..9).filter(|d| digits & (1 << d) != 0) {
+                                    step.eliminations[digit] = self.candidates[digit] & rest;
+                                }
+
+                                if !step.is_empty() {
+                                    step.sets.push(self.rules.describe_set(set_index));
+                                    return Some(step);
+                                }
+                            }
+                        }
+                    }
+                },
+                // one of the digits is locked into the roof, so the other can't be there
+                4 if in_line => {
+                    for set_index in self.shared_sets(roof_mask) {
+                        for (x, y) in [(a, b), (b, a)] {
+                            if self.candidates[x] & self.rules.sets[set_index] & !self.cell_complete & !roof_mask == 0 {
+                                step.eliminations[y] |= self.candidates[y] & roof_mask;
+                                step.sets.push(self.rules.describe_set(set_index));
+                            }
+                        }
+
+                        if !step.is_empty() {
+                            return Some(step);
+                        }
+                        step.sets.clear();
+                    }
+                },
+                // diagonal roof with a digit that only appears on the rectangle in both of its
+                // rows or both of its columns, so the roof can't have it
+                6 if roof.len() == 2 && !in_line && self.sides_linked(&rectangle) => {
+                    for digit in [a, b] {
+                        let confined = |pairs: [(usize, usize); 2]| pairs.iter().all(|(p, q)| {
+                            let pair = (1 << rectangle.corners[*p]) | (1 << rectangle.corners[*q]);
+                            self.shared_sets(pair).iter().any(|s| self.candidates[digit] & self.rules.sets[*s] & !self.cell_complete & !pair == 0)
+                        });
+
+                        if confined([(0, 1), (2, 3)]) || confined([(0, 2), (1, 3)]) {
+                            step.eliminations[digit] = self.candidates[digit] & roof_mask;
+                        }
+                    }
+                },
+                _ => {},
+            }
+
+            if !step.is_empty() {
+                return Some(step);
+            }
+        }
+
+        None
+    }
+
+    /// A corner with only `a` and `b`, and a digit that is locked into the rectangle along both
+    /// sides of the opposite corner. If that opposite corner were the other digit, the two
+    /// corners beside it would both be forced and the pattern would be deadly.
+    pub(super) fn hidden_rectangle(&self) -> Option<Step> {
+        for rectangle in self.rectangles() {
+            if !self.sides_linked(&rectangle) {
+                continue;
+            }
+
+            for corner in 0..4 {
+                if self.get_candidates(rectangle.corners[corner]) != rectangle.digits() {
+                    continue;
+                }
+
+                let opposite = Rectangle::opposite(corner);
+                let sides = [corner ^ 1, corner ^ 2];
+
+                for (x, y) in [(rectangle.a, rectangle.b), (rectangle.b, rectangle.a)] {
+                    let locked = sides.iter().all(|side| {
+                        let pair = (1 << rectangle.corners[opposite]) | (1 << rectangle.corners[*side]);
+                        self.shared_sets(pair).iter().any(|s| self.candidates[x] & self.rules.sets[*s] & !self.cell_complete & !pair == 0)
+                    });
+
+                    if locked && self.digit_is_candidate(rectangle.corners[opposite], y) {
+                        let mut step = self.rectangle_step(Technique::HiddenRectangle, &rectangle);
+                        step.eliminations[y] = 1 << rectangle.corners[opposite];
+                        return Some(step);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Bivalue universal grave plus one: every unsolved cell has two candidates except one with
+    /// three. Without the digit that appears three times in that cell's sets every digit would be
+    /// left twice in every set, which can't have a unique solution, so the cell is that digit.
+    /// Cage sums and chess moves can break the grave, so it isn't used with either.
+    pub(super) fn bug_plus_one(&self) -> Option<Step> {
+        if !self.rules.cages.is_empty() || !self.rules.anti_moves().is_empty() {
+            return None;
+        }
+
+        let unsolved = get_set_indexes(!self.cell_complete & 0x1FFFFFFFFFFFFFFFFFFFF);
+        let mut extra = None;
+
+        for index in unsolved {
+            match self.get_candidates(index).count_ones() {
+                2 => {},
+                3 if extra.is_none() => {extra = Some(index)},
+                _ => {return None},
+            }
+        }
+
+        let index = extra?;
+
+        for digit in self.candidates_vec(index) {
+            let mut brd = self.clone();
+            brd.candidates[digit] &= !(1 << index);
+
+            let grave = brd.rules.sets.iter().all(|set| {
+                (0..9).all(|d| matches!((brd.candidates[d] & set & !brd.cell_complete).count_ones(), 0 | 2))
+            });
+
+            if grave {
+                let mut step = Step::new(Technique::BUGPlusOne);
+                step.placements[digit] = 1 << index;
+                return Some(step);
+            }
+        }
+
+        None
+    }
+}
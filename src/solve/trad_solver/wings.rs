@@ -0,0 +1,150 @@
+use crate::rules::describe_cell;
+use super::{BitmaskBoard, combinations, get_set_indexes};
+use super::steps::{describe_digits, Step, Technique};
+
+impl BitmaskBoard {
+
+    /// Checks whether some cells with as many digits between them as there are cells make a wing:
+    /// every digit but one has all of its cells seeing each other, so the odd digit out has to be
+    /// in one of its cells. Returns that digit and the cells it can be removed from.
+    fn wing_eliminations(&self, cells: &[u8]) -> Option<(usize, u128)> {
+        let digits = cells.iter().fold(0, |acc, i| acc | self.get_candidates(*i));
+
+        if digits.count_ones() as usize != cells.len() {
+            return None;
+        }
+
+        let cell_mask = cells.iter().fold(0u128, |acc, i| acc | (1 << i));
+        let mut unrestricted = None;
+
+        for digit in 0..9 {
+            if digits & (1 << digit) == 0 {
+                continue;
+            }
+
+            let holders = get_set_indexes(self.candidates[digit] & cell_mask);
+            let restricted = combinations(&holders, 2).iter().all(|pair| self.bitmask_get(self.peers(pair[0]), pair[1]));
+
+            if !restricted {
+                if unrestricted.is_some() {
+                    return None;
+                }
+                unrestricted = Some(digit);
+            }
+        }
+
+        let digit = unrestricted?;
+        let holders = self.candidates[digit] & cell_mask;
+        let seen = get_set_indexes(holders).iter().fold(u128::MAX, |acc, i| acc & self.peers(*i));
+        let eliminate = self.candidates[digit] & !self.cell_complete & seen & !cell_mask;
+
+        if eliminate == 0 {
+            return None;
+        }
+
+        Some((digit, eliminate))
+    }
+
+    fn wing_step(&self, technique: Technique, pivot: u8, pincers: &[u8], digit: usize, eliminate: u128) -> Step {
+        let pincers: Vec<String> = pincers.iter().map(|i| describe_cell(*i)).collect();
+
+        let mut step = Step::new(technique);
+        step.detail = format!("{} pivot {} pincers {{{}}}", digit + 1, describe_cell(pivot), pincers.join(", "));
+        step.eliminations[digit] = eliminate;
+        step
+    }
+
+    /// XY-Wing (a bivalue pivot and two bivalue pincers), XYZ-Wing (a trivalue pivot) and
+    /// WXYZ-Wing (four cells with four digits), all with the pivot seeing every pincer.
+    pub(super) fn xyz_wing(&self, size: usize) -> Option<Step> {
+        let technique = match size {
+            2 => Technique::XYWing,
+            3 => Technique::XYZWing,
+            4 => Technique::WXYZWing,
+            _ => unreachable!("only XY, XYZ and WXYZ wings are looked for"),
+        };
+
+        let pincer_count = if size == 2 {2} else {size - 1};
+
+        for pivot in get_set_indexes(!self.cell_complete & 0x1FFFFFFFFFFFFFFFFFFFF) {
+            let pivot_count = self.get_candidates(pivot).count_ones() as usize;
+
+            // XY and XYZ wings have a bivalue or trivalue pivot, WXYZ can have anything up to four
+            if (size < 4 && pivot_count != size) || !(2..=4).contains(&pivot_count) {
+                continue;
+            }
+
+            let pincer_options: Vec<u8> = get_set_indexes(self.peers(pivot) & !self.cell_complete)
+                .into_iter()
+                .filter(|i| {
+                    let count = self.get_candidates(*i).count_ones() as usize;
+                    if size < 4 {count == 2} else {(2..=4).contains(&count)}
+                })
+                .collect();
+
+            for pincers in combinations(&pincer_options, pincer_count) {
+                let mut cells = pincers.clone();
+                cells.push(pivot);
+
+                if let Some((digit, eliminate)) = self.wing_eliminations(&cells) {
+                    // a real XY-Wing keeps the eliminated digit out of its pivot
+                    if size == 2 && self.digit_is_candidate(pivot, digit) {
+                        continue;
+                    }
+
+                    return Some(self.wing_step(technique, pivot, &pincers, digit, eliminate));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Two bivalue cells with the same digits {x,y} that don't see each other, joined by a strong
+    /// link on x whose ends see one cell each. One of them has to be y, so anything seeing both
+    /// can't be y.
+    pub(super) fn w_wing(&self) -> Option<Step> {
+        let bivalue: Vec<u8> = get_set_indexes(!self.cell_complete & 0x1FFFFFFFFFFFFFFFFFFFF)
+            .into_iter()
+            .filter(|i| self.get_candidates(*i).count_ones() == 2)
+            .collect();
+
+        for pair in combinations(&bivalue, 2) {
+            let (a, b) = (pair[0], pair[1]);
+            let digits = self.get_candidates(a);
+
+            if digits != self.get_candidates(b) || self.bitmask_get(self.peers(a), b) {
+                continue;
+            }
+
+            for (x, y) in [(digits.trailing_zeros() as usize, 15 - digits.leading_zeros() as usize), (15 - digits.leading_zeros() as usize, digits.trailing_zeros() as usize)] {
+                let eliminate = self.candidates[y] & !self.cell_complete & self.peers(a) & self.peers(b);
+
+                if eliminate == 0 {
+                    continue;
+                }
+
+                for (set_index, set) in self.rules.sets.iter().enumerate() {
+                    let link = self.candidates[x] & set & !self.cell_complete;
+
+                    if link.count_ones() != 2 || link & ((1 << a) | (1 << b)) != 0 {
+                        continue;
+                    }
+
+                    let ends = get_set_indexes(link);
+                    let joined = |p: u8, q: u8| self.bitmask_get(self.peers(p), a) && self.bitmask_get(self.peers(q), b);
+
+                    if joined(ends[0], ends[1]) || joined(ends[1], ends[0]) {
+                        let mut step = Step::new(Technique::WWing);
+                        step.detail = format!("{} {}, {} linked by {}", describe_digits(digits), describe_cell(a), describe_cell(b), x + 1);
+                        step.sets.push(self.rules.describe_set(set_index));
+                        step.eliminations[y] = eliminate;
+                        return Some(step);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
@@ -1,40 +1,280 @@
-use std::task::Context;
-use std::vec;
+use std::fmt;
 
-use z3::ast::{self, Ast, Int};
+use z3::ast::{Ast, Bool, Int};
+use z3::{Context, Model, SatResult, Solver};
 
-use crate::board::{Board, BasicBoard};
+use crate::board::BasicBoard;
+use crate::rules::describe_cell;
 use crate::solve::Solve;
 
-impl Solve for BasicBoard {
-    fn solve(&mut self) {
+/// What Z3 has to say about how many ways a board can be completed.
+#[derive(Debug, Clone)]
+pub enum Uniqueness {
+    Unique,
+    /// Holds a second completion that differs from the one written into the board.
+    Multiple(Box<BasicBoard>),
+    Unsat,
+}
 
-        // init z3 instance
-        let ctx = &z3::Context::default();
-        let solver = z3::Solver::new(&ctx);
+/// A smallest set of givens and rule sets that can't all hold at once, from `BasicBoard::explain_unsat`.
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub cells: Vec<String>,
+    pub sets: Vec<String>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.cells.join(", "))?;
+
+        if !self.sets.is_empty() {
+            if !self.cells.is_empty() {
+                write!(f, " together with ")?;
+            }
+            write!(f, "{}", self.sets.join(", "))?;
+        }
+
+        write!(f, " can't all be satisfied")
+    }
+}
 
+// what a constraint handed to z3 came from, so an unsat core can be explained
+#[derive(Debug, Clone, Copy)]
+enum Reason {
+    Cell(usize),
+    Set(usize),
+    Cage(usize),
+    AntiMove(usize),
+}
+
+impl BasicBoard {
+
+    fn z3_variables<'ctx>(&self, ctx: &'ctx Context, solver: &Solver<'ctx>) -> Vec<Int<'ctx>> {
         let mut variables = Vec::new();
-        let cells = self.completed_cells();
 
         for i in 0..9 {
             for j in 0..9 {
-                let cell_value = cells[(i*9)+j];
                 let cell_name = format!("cell_{i}_{j}");
-                let z3cell = Int::new_const(&ctx, cell_name);
+                let z3cell = Int::new_const(ctx, cell_name);
+
+                solver.assert(&z3cell.le(&Int::from_i64(ctx, 9)));
+                solver.assert(&z3cell.ge(&Int::from_i64(ctx, 1)));
+
                 variables.push(z3cell);
-                solver.assert(&z3cell.le(&Int::from_i64(&ctx, 9)));
-                solver.assert(&z3cell.ge(&Int::from_i64(&ctx, 1)));
             }
         }
 
-        let groups = vec![];
+        variables
+    }
 
-        for i in 0..9 {
-            for j in 0..9 {
+    fn z3_constraints<'ctx>(&self, ctx: &'ctx Context, variables: &[Int<'ctx>]) -> Vec<(Reason, Bool<'ctx>)> {
+        let mut constraints = vec![];
+
+        // anything that has already been ruled out stays ruled out (this covers the givens too)
+        for (index, z3cell) in variables.iter().enumerate() {
+            let ruled_out: Vec<Bool> = (0..9)
+                .filter(|digit| !self.candidates[index][*digit])
+                .map(|digit| z3cell._eq(&Int::from_i64(ctx, digit as i64 + 1)).not())
+                .collect();
+
+            if !ruled_out.is_empty() {
+                let ruled_out: Vec<&Bool> = ruled_out.iter().collect();
+                constraints.push((Reason::Cell(index), Bool::and(ctx, &ruled_out)));
             }
         }
 
-        solver.assert(&ctx, );
+        // every set in the rules is just "all of these cells are different"
+        for (set_index, set) in self.rules.sets.iter().enumerate() {
+            let group: Vec<&Int> = (0..81)
+                .filter(|i| (set >> i) % 2 != 0)
+                .map(|i| &variables[i])
+                .collect();
+
+            constraints.push((Reason::Set(set_index), Int::distinct(ctx, &group)));
+        }
+
+        // killer cages are all different too, and have to add up
+        for (cage_index, cage) in self.rules.cages.iter().enumerate() {
+            let group: Vec<&Int> = (0..81)
+                .filter(|i| (cage.cells >> i) % 2 != 0)
+                .map(|i| &variables[i])
+                .collect();
+
+            let sum = Int::add(ctx, &group)._eq(&Int::from_i64(ctx, cage.sum as i64));
+            constraints.push((Reason::Cage(cage_index), Bool::and(ctx, &[&Int::distinct(ctx, &group), &sum])));
+        }
+
+        // chess moves only ever rule out a pair of cells sharing one of the move's digits
+        for (move_index, anti_move) in self.rules.anti_moves().iter().enumerate() {
+            let mut pairs = vec![];
+
+            for a in 0..81 {
+                for b in (a + 1)..81 {
+                    if (anti_move.piece.moves()[a] >> b) % 2 == 0 {
+                        continue;
+                    }
+
+                    if anti_move.digits == 0x1FF {
+                        pairs.push(variables[a]._eq(&variables[b]).not());
+                    } else {
+                        for digit in (0..9).filter(|d| anti_move.digits & (1 << d) != 0) {
+                            let value = Int::from_i64(ctx, digit as i64 + 1);
+                            pairs.push(Bool::and(ctx, &[&variables[a]._eq(&value), &variables[b]._eq(&value)]).not());
+                        }
+                    }
+                }
+            }
+
+            let pairs: Vec<&Bool> = pairs.iter().collect();
+            constraints.push((Reason::AntiMove(move_index), Bool::and(ctx, &pairs)));
+        }
+
+        constraints
+    }
+
+    fn z3_encode<'ctx>(&self, ctx: &'ctx Context, solver: &Solver<'ctx>) -> Vec<Int<'ctx>> {
+        let variables = self.z3_variables(ctx, solver);
+
+        for (_, constraint) in self.z3_constraints(ctx, &variables) {
+            solver.assert(&constraint);
+        }
+
+        variables
+    }
+
+    fn z3_read_model(&mut self, model: &Model, variables: &[Int]) {
+        for (index, z3cell) in variables.iter().enumerate() {
+            let value = model.eval(z3cell, true).unwrap().as_i64().unwrap() as u8;
+            self.cells[index] = value;
+            self.candidates[index] = [false; 9];
+            self.candidates[index][value as usize - 1] = true;
+        }
+    }
+
+    /// Solves the board with Z3, then blocks that solution and asks again to find out if it was
+    /// the only one. The first solution is written into the board either way.
+    pub fn check_uniqueness(&mut self) -> Uniqueness {
+        let cfg = z3::Config::new();
+        let ctx = &z3::Context::new(&cfg);
+        let solver = z3::Solver::new(ctx);
+
+        let variables = self.z3_encode(ctx, &solver);
+
+        if solver.check() != SatResult::Sat {
+            return Uniqueness::Unsat;
+        }
+
+        self.z3_read_model(&solver.get_model().unwrap(), &variables);
+
+        // at least one cell has to be different next time round
+        let differences: Vec<Bool> = variables.iter()
+            .zip(self.cells)
+            .map(|(z3cell, value)| z3cell._eq(&Int::from_i64(ctx, value as i64)).not())
+            .collect();
+        let differences: Vec<&Bool> = differences.iter().collect();
+
+        solver.assert(&Bool::or(ctx, &differences));
+
+        if solver.check() != SatResult::Sat {
+            return Uniqueness::Unique;
+        }
+
+        let mut second = self.clone();
+        second.z3_read_model(&solver.get_model().unwrap(), &variables);
+
+        Uniqueness::Multiple(Box::new(second))
+    }
+
+    /// If the board can't be solved, names a minimal set of cells and rule sets that clash.
+    /// Returns None when the board does have a solution.
+    pub fn explain_unsat(&self) -> Option<Conflict> {
+        let cfg = z3::Config::new();
+        let ctx = &z3::Context::new(&cfg);
+        let solver = z3::Solver::new(ctx);
+
+        let variables = self.z3_variables(ctx, &solver);
+        let constraints = self.z3_constraints(ctx, &variables);
+
+        // each constraint only applies while its tracking literal is assumed to be true
+        let mut trackers = vec![];
+        for (i, (_, constraint)) in constraints.iter().enumerate() {
+            let tracker = Bool::new_const(ctx, format!("track_{i}"));
+            solver.assert(&tracker.implies(constraint));
+            trackers.push(tracker);
+        }
+
+        if solver.check_assumptions(&trackers) != SatResult::Unsat {
+            return None;
+        }
+
+        // z3's cores aren't minimal, so keep throwing things out while it stays unsat
+        let mut core: Vec<usize> = solver.get_unsat_core().iter()
+            .filter_map(|lit| trackers.iter().position(|t| t == lit))
+            .collect();
+
+        let mut i = 0;
+        while i < core.len() {
+            let mut without = core.clone();
+            without.remove(i);
+
+            let assumptions: Vec<Bool> = without.iter().map(|t| trackers[*t].clone()).collect();
+
+            if solver.check_assumptions(&assumptions) == SatResult::Unsat {
+                core = without;
+            } else {
+                i += 1;
+            }
+        }
+
+        let mut conflict = Conflict {cells: vec![], sets: vec![]};
+
+        for t in core {
+            match constraints[t].0 {
+                Reason::Cell(index) => {
+                    let allowed: Vec<String> = (0..9)
+                        .filter(|digit| self.candidates[index][*digit])
+                        .map(|digit| (digit + 1).to_string())
+                        .collect();
+
+                    let cell = describe_cell(index as u8);
+
+                    conflict.cells.push(match allowed.len() {
+                        1 => format!("{cell}={}", allowed[0]),
+                        _ => format!("{cell} in {{{}}}", allowed.join(",")),
+                    });
+                }
+                Reason::Set(set_index) => {
+                    conflict.sets.push(self.rules.describe_set(set_index));
+                }
+                Reason::Cage(cage_index) => {
+                    conflict.sets.push(format!("the {}", self.rules.cages[cage_index]));
+                }
+                Reason::AntiMove(move_index) => {
+                    conflict.sets.push(format!("the {} rule", self.rules.anti_moves()[move_index]));
+                }
+            }
+        }
+
+        Some(conflict)
+    }
+}
+
+impl Solve for BasicBoard {
+    fn solve(&mut self) -> bool {
+
+        // init z3 instance
+        let cfg = z3::Config::new();
+        let ctx = &z3::Context::new(&cfg);
+        let solver = z3::Solver::new(ctx);
+
+        let variables = self.z3_encode(ctx, &solver);
+
+        if solver.check() != SatResult::Sat {
+            return false;
+        }
+
+        self.z3_read_model(&solver.get_model().unwrap(), &variables);
 
+        true
     }
 }